@@ -0,0 +1,374 @@
+use docx_parser::{Document, Query};
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+
+#[derive(Parser)]
+#[command(
+    name = "docx-reader",
+    version,
+    about = "Read docx, odt and rtf documents"
+)]
+struct Cli {
+    /// Password for encrypted docx files
+    #[arg(long, global = true)]
+    password: Option<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the outline, indented by level
+    Outline {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Print every paragraph, as text or the same versioned json as json
+    Dump {
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+        file: PathBuf,
+    },
+    /// Print the paragraphs matching a query as path:index:text, like grep
+    Search {
+        query: String,
+        #[arg(short = 'c', long)]
+        match_case: bool,
+        /// Only search headings
+        #[arg(short = 'o', long)]
+        only_outline: bool,
+        /// The query is a regular expression, like \b(19|20)\d\d\b
+        #[arg(short = 'E', long)]
+        regex: bool,
+        /// Only match whole words
+        #[arg(short = 'w', long)]
+        whole_word: bool,
+        /// Match letters with or without accents, so cafe matches café
+        #[arg(long)]
+        ignore_diacritics: bool,
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Print the document as versioned json, see docx-parser/schema
+    Json {
+        /// Only the heading at this paragraph and everything under it
+        #[arg(long, value_name = "INDEX")]
+        section: Option<usize>,
+        file: PathBuf,
+    },
+    /// Write the document as an EPUB book
+    Epub {
+        /// Start a chapter at each heading at this level or higher, 1 being
+        /// word's Heading 1
+        #[arg(
+            long,
+            value_name = "LEVEL",
+            default_value_t = 2,
+            value_parser = clap::value_parser!(u32).range(1..=9)
+        )]
+        split_level: u32,
+        file: PathBuf,
+        output: PathBuf,
+    },
+    /// Print paragraph, heading and word counts
+    Stats {
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Text,
+    Json,
+}
+
+#[derive(Serialize)]
+struct Stats {
+    path: PathBuf,
+    paragraphs: usize,
+    headings: usize,
+    // keyed by outline level, starting at 0 like Para::outline_level
+    headings_by_level: BTreeMap<u32, usize>,
+    words: usize,
+    characters: usize,
+}
+
+// like grep: 0 if something was found, 1 if not, 2 if a file couldn't be read
+const FOUND: u8 = 0;
+const NOT_FOUND: u8 = 1;
+const FAILED: u8 = 2;
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let mut out = io::stdout().lock();
+    let result = match cli.command {
+        Command::Outline { files } => outline(&mut out, &files, cli.password.as_deref()),
+        Command::Dump { format, file } => dump(&mut out, &file, format, cli.password.as_deref()),
+        Command::Search {
+            query,
+            match_case,
+            only_outline,
+            regex,
+            whole_word,
+            ignore_diacritics,
+            files,
+        } => {
+            let query = Query {
+                text: query,
+                match_case,
+                only_outline,
+                regex,
+                whole_word,
+                ignore_diacritics,
+            };
+            search(&mut out, &query, &files, cli.password.as_deref())
+        }
+        Command::Json { section, file } => json(&mut out, &file, section, cli.password.as_deref()),
+        Command::Epub {
+            split_level,
+            file,
+            output,
+        } => epub(&file, &output, split_level, cli.password.as_deref()),
+        Command::Stats { format, files } => {
+            stats(&mut out, &files, format, cli.password.as_deref())
+        }
+    };
+    match result.and_then(|code| out.flush().map(|_| code)) {
+        Ok(code) => ExitCode::from(code),
+        // the reader went away, e.g. piped into head
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => ExitCode::from(FOUND),
+        Err(e) => {
+            eprintln!("docx-reader: {}", e);
+            ExitCode::from(FAILED)
+        }
+    }
+}
+
+// damaged files still load, so warnings go to stderr and the output carries on
+fn load(path: &Path, password: Option<&str>) -> Option<Document> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("docx-reader: {}: {}", path.display(), e);
+            return None;
+        }
+    };
+    let mut doc = Document::new();
+    if let Err(e) = doc.load_bytes_with_password(&bytes, password) {
+        eprintln!("docx-reader: {}: {}", path.display(), e);
+        return None;
+    }
+    for warning in doc.warnings.iter() {
+        eprintln!("docx-reader: {}: warning: {}", path.display(), warning);
+    }
+    Some(doc)
+}
+
+fn outline(out: &mut impl Write, files: &[PathBuf], password: Option<&str>) -> io::Result<u8> {
+    let mut code = FOUND;
+    for (i, path) in files.iter().enumerate() {
+        let doc = match load(path, password) {
+            Some(doc) => doc,
+            None => {
+                code = FAILED;
+                continue;
+            }
+        };
+        if files.len() > 1 {
+            if i > 0 {
+                writeln!(out)?;
+            }
+            writeln!(out, "==> {} <==", path.display())?;
+        }
+        for para in doc.outline_paras.iter() {
+            let indent = "  ".repeat(para.outline_level.unwrap_or(0) as usize);
+            let text: String = para.runs.iter().map(|run| run.text.as_str()).collect();
+            writeln!(out, "{}{}", indent, text)?;
+        }
+    }
+    Ok(code)
+}
+
+fn dump(
+    out: &mut impl Write,
+    path: &Path,
+    format: Format,
+    password: Option<&str>,
+) -> io::Result<u8> {
+    match format {
+        Format::Text => {
+            let doc = match load(path, password) {
+                Some(doc) => doc,
+                None => return Ok(FAILED),
+            };
+            for para in doc.paras.iter() {
+                writeln!(out, "{}", para.text())?;
+            }
+            Ok(FOUND)
+        }
+        // the raw paragraphs aren't versioned, so scripts get what json prints
+        Format::Json => json(out, path, None, password),
+    }
+}
+
+fn json(
+    out: &mut impl Write,
+    path: &Path,
+    section: Option<usize>,
+    password: Option<&str>,
+) -> io::Result<u8> {
+    let doc = match load(path, password) {
+        Some(doc) => doc,
+        None => return Ok(FAILED),
+    };
+    // the file name is the title when the properties don't have one
+    let name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let export = doc.export(&name);
+    let export = match section {
+        Some(index) => match export.section(index) {
+            Some(section) => section,
+            None => {
+                eprintln!(
+                    "docx-reader: {}: paragraph {} doesn't exist",
+                    path.display(),
+                    index
+                );
+                return Ok(FAILED);
+            }
+        },
+        None => export,
+    };
+    writeln!(out, "{}", export.to_json(&doc.info))?;
+    Ok(FOUND)
+}
+
+fn epub(path: &Path, output: &Path, split_level: u32, password: Option<&str>) -> io::Result<u8> {
+    let doc = match load(path, password) {
+        Some(doc) => doc,
+        None => return Ok(FAILED),
+    };
+    let name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    // outline levels start at 0
+    let epub = doc.export(&name).to_epub(&doc.info, split_level - 1);
+    if let Err(e) = fs::write(output, epub) {
+        eprintln!("docx-reader: {}: {}", output.display(), e);
+        return Ok(FAILED);
+    }
+    Ok(FOUND)
+}
+
+fn search(
+    out: &mut impl Write,
+    query: &Query,
+    files: &[PathBuf],
+    password: Option<&str>,
+) -> io::Result<u8> {
+    let matcher = match query.matcher() {
+        Ok(matcher) => matcher,
+        Err(e) => {
+            eprintln!("docx-reader: {}", e);
+            return Ok(FAILED);
+        }
+    };
+    let mut found = false;
+    let mut failed = false;
+    for path in files.iter() {
+        let doc = match load(path, password) {
+            Some(doc) => doc,
+            None => {
+                failed = true;
+                continue;
+            }
+        };
+        for para in doc.paras.iter() {
+            let text = para.text();
+            if !matcher.find(para, &text).is_empty() {
+                found = true;
+                writeln!(out, "{}:{}:{}", path.display(), para.index, text)?;
+            }
+        }
+    }
+    Ok(match (failed, found) {
+        (true, _) => FAILED,
+        (false, true) => FOUND,
+        (false, false) => NOT_FOUND,
+    })
+}
+
+fn stats(
+    out: &mut impl Write,
+    files: &[PathBuf],
+    format: Format,
+    password: Option<&str>,
+) -> io::Result<u8> {
+    let mut code = FOUND;
+    let mut all_stats = Vec::new();
+    for path in files.iter() {
+        let doc = match load(path, password) {
+            Some(doc) => doc,
+            None => {
+                code = FAILED;
+                continue;
+            }
+        };
+        let mut stats = Stats {
+            path: path.clone(),
+            paragraphs: doc.paras.len(),
+            headings: doc.outline_paras.len(),
+            headings_by_level: BTreeMap::new(),
+            words: 0,
+            characters: 0,
+        };
+        for para in doc.outline_paras.iter() {
+            *stats
+                .headings_by_level
+                .entry(para.outline_level.unwrap_or(0))
+                .or_insert(0) += 1;
+        }
+        for para in doc.paras.iter() {
+            let text = para.text();
+            stats.words += text.split_whitespace().count();
+            stats.characters += text.chars().count();
+        }
+        all_stats.push(stats);
+    }
+    match format {
+        Format::Text => {
+            for (i, stats) in all_stats.iter().enumerate() {
+                if i > 0 {
+                    writeln!(out)?;
+                }
+                writeln!(out, "{}", stats.path.display())?;
+                writeln!(out, "  paragraphs  {}", stats.paragraphs)?;
+                writeln!(out, "  headings    {}", stats.headings)?;
+                for (level, count) in stats.headings_by_level.iter() {
+                    // levels are shown 1-based, like word's Heading 1
+                    writeln!(out, "    level {}   {}", level + 1, count)?;
+                }
+                writeln!(out, "  words       {}", stats.words)?;
+                writeln!(out, "  characters  {}", stats.characters)?;
+            }
+        }
+        Format::Json => {
+            serde_json::to_writer(&mut *out, &all_stats)?;
+            writeln!(out)?;
+        }
+    }
+    Ok(code)
+}
@@ -0,0 +1,105 @@
+// generates a large debate file: pockets, hats, blocks and tags, each tag followed
+// by a cite and a card with underlined and highlighted runs. the same seed always
+// gives the same document, so runs can be compared.
+
+#[path = "../../tests/common/mod.rs"]
+mod docx;
+
+use docx::{heading_style, style, DocxBuilder};
+
+#[rustfmt::skip]
+const WORDS: &[&str] = &[
+    "nuclear", "war", "causes", "extinction", "economic", "decline", "hegemony", "collapse", "the",
+    "of", "and", "a", "to", "in", "is", "that", "escalation", "deterrence", "China", "Russia",
+    "climate", "change", "warming", "impact", "probability", "magnitude", "timeframe", "policy",
+    "federal", "government", "should", "increase", "reduce", "cooperation", "conflict", "Nuclear",
+    "proliferation", "alliances", "credibility", "risk", "global", "security",
+];
+
+// about 16,500 paragraphs and 5 MB of text, the size of a big masterfile
+const POCKETS: usize = 10;
+const CARDS_PER_BLOCK: usize = 8;
+const BLOCKS_PER_HAT: usize = 10;
+const HATS_PER_POCKET: usize = 5;
+
+// small deterministic generator, so benchmarks don't need a rand dependency
+struct Words(u64);
+impl Words {
+    fn next(&mut self) -> &'static str {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        WORDS[(self.0 >> 33) as usize % WORDS.len()]
+    }
+    fn sentence(&mut self, len: usize) -> String {
+        (0..len).map(|_| self.next()).collect::<Vec<_>>().join(" ")
+    }
+}
+
+fn para(style: &str, runs: &str) -> String {
+    format!(
+        r#"<w:p><w:pPr><w:pStyle w:val="{}"/></w:pPr>{}</w:p>"#,
+        style, runs
+    )
+}
+
+fn run(run_properties: &str, text: &str) -> String {
+    format!(
+        r#"<w:r><w:rPr>{}</w:rPr><w:t xml:space="preserve">{} </w:t></w:r>"#,
+        run_properties, text
+    )
+}
+
+fn card(words: &mut Words) -> String {
+    let mut runs = String::new();
+    for i in 0..12 {
+        let run_properties = match i % 4 {
+            1 => r#"<w:rStyle w:val="Underline"/>"#,
+            2 => r#"<w:rStyle w:val="Underline"/><w:highlight w:val="cyan"/>"#,
+            _ => "",
+        };
+        runs.push_str(&run(run_properties, &words.sentence(10)));
+    }
+    para("Normal", &runs)
+}
+
+pub fn generate() -> Vec<u8> {
+    let styles = [
+        style("Normal", None, r#"<w:sz w:val="22"/>"#),
+        heading_style("Heading1", 0, r#"<w:b/><w:sz w:val="52"/>"#),
+        heading_style("Heading2", 1, r#"<w:b/><w:sz w:val="44"/>"#),
+        heading_style(
+            "Heading3",
+            2,
+            r#"<w:b/><w:u w:val="single"/><w:sz w:val="32"/>"#,
+        ),
+        heading_style("Heading4", 3, r#"<w:b/><w:sz w:val="26"/>"#),
+        style("Underline", Some("Normal"), r#"<w:u w:val="single"/>"#),
+        style("Cite", Some("Normal"), r#"<w:b/><w:sz w:val="26"/>"#),
+    ]
+    .concat();
+    let mut words = Words(0);
+    let mut body = String::new();
+    for _ in 0..POCKETS {
+        body.push_str(&para("Heading1", &run("", &words.sentence(3))));
+        for _ in 0..HATS_PER_POCKET {
+            body.push_str(&para("Heading2", &run("", &words.sentence(4))));
+            for _ in 0..BLOCKS_PER_HAT {
+                body.push_str(&para("Heading3", &run("", &words.sentence(5))));
+                for _ in 0..CARDS_PER_BLOCK {
+                    body.push_str(&para("Heading4", &run("", &words.sentence(15))));
+                    let cite = [
+                        run(r#"<w:rStyle w:val="Cite"/>"#, "Smith 22"),
+                        run("", &words.sentence(20)),
+                    ]
+                    .concat();
+                    body.push_str(&para("Normal", &cite));
+                    body.push_str(&card(&mut words));
+                    body.push_str(&para("Normal", ""));
+                }
+            }
+        }
+    }
+    DocxBuilder::new().styles(&styles).body(&body).build()
+}
@@ -0,0 +1,33 @@
+mod common;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use docx_parser::{Document, Search};
+
+use std::fs;
+
+fn load(c: &mut Criterion) {
+    let bytes = common::generate();
+    // load_file is what the app calls, so go through the file system like it does
+    let path = std::env::temp_dir().join("docx-parser-bench.docx");
+    fs::write(&path, &bytes).unwrap();
+    let path = path.to_str().unwrap();
+
+    let mut group = c.benchmark_group("load");
+    group.sample_size(20);
+    group.bench_function("load_file", |b| {
+        b.iter(|| {
+            let mut doc = Document::new();
+            doc.load_file(path).unwrap();
+            doc
+        })
+    });
+    // the text the app builds for every paragraph after loading, for search
+    let mut doc = Document::new();
+    doc.load_file(path).unwrap();
+    let mut search = Search::new();
+    group.bench_function("para_texts", |b| b.iter(|| search.set_paras(&doc.paras)));
+    group.finish();
+}
+
+criterion_group!(benches, load);
+criterion_main!(benches);
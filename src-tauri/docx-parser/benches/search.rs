@@ -0,0 +1,62 @@
+mod common;
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use docx_parser::{Document, Query, Search};
+
+// how many results the search sidebar asks for at a time
+const PAGE: usize = 100;
+
+fn query(text: &str) -> Query {
+    Query {
+        text: text.to_string(),
+        match_case: false,
+        only_outline: false,
+        regex: false,
+        whole_word: false,
+        ignore_diacritics: false,
+    }
+}
+
+fn search(c: &mut Criterion) {
+    let mut doc = Document::new();
+    doc.load_bytes(&common::generate()).unwrap();
+    let paras = &doc.paras;
+    let mut search = Search::new();
+    search.set_paras(paras);
+
+    let mut group = c.benchmark_group("search");
+    group.sample_size(20);
+    // a new query, nothing to reuse
+    group.bench_function("cold/first_page", |b| {
+        b.iter(|| {
+            search.clear();
+            search.search(&query("nuclear"), paras, 0, PAGE).unwrap()
+        })
+    });
+    group.bench_function("cold/all", |b| {
+        b.iter(|| {
+            search.clear();
+            search
+                .search(&query("nuclear"), paras, 0, usize::MAX)
+                .unwrap()
+        })
+    });
+    // typing more of the query narrows the results of the last one
+    search.clear();
+    search.search(&query("nuc"), paras, 0, usize::MAX).unwrap();
+    group.bench_function("narrowed/all", |b| {
+        b.iter_batched(
+            || search.clone(),
+            |mut search| {
+                search
+                    .search(&query("nuclear"), paras, 0, usize::MAX)
+                    .unwrap()
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, search);
+criterion_main!(benches);
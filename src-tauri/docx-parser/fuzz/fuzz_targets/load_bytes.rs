@@ -0,0 +1,11 @@
+#![no_main]
+
+use docx_parser::Document;
+use libfuzzer_sys::fuzz_target;
+
+// everything load_file does after reading the file: format detection, unzipping,
+// recovery of damaged zips, and every format's parser
+fuzz_target!(|data: &[u8]| {
+    let mut doc = Document::new();
+    let _ = doc.load_bytes_with_password(data, Some("password"));
+});
@@ -0,0 +1,13 @@
+#![no_main]
+
+use docx_parser::Document;
+use libfuzzer_sys::fuzz_target;
+
+// styles.xml and document.xml, split at the first NUL so both parts get fuzzed
+// together and paragraphs can refer to styles
+fuzz_target!(|data: &str| {
+    let (styles, document) = data.split_once('\0').unwrap_or(("", data));
+    let mut doc = Document::new();
+    doc.load_style_map(styles);
+    doc.load_paras(document);
+});
@@ -0,0 +1,10 @@
+#![no_main]
+
+use docx_parser::Document;
+use libfuzzer_sys::fuzz_target;
+
+// the contents of word/styles.xml
+fuzz_target!(|data: &str| {
+    let mut doc = Document::new();
+    doc.load_style_map(data);
+});
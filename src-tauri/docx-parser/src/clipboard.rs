@@ -0,0 +1,171 @@
+use crate::document::{Para, Run};
+use crate::export::Export;
+use crate::html::push_para;
+
+use std::fmt::Write;
+
+/// Part of a document in the formats other apps paste from. Word reads the
+/// rtf, browsers and most other editors the html.
+#[derive(Clone, Debug)]
+pub struct Clipboard {
+    /// Paragraphs separated by newlines.
+    pub text: String,
+    pub html: String,
+    pub rtf: String,
+}
+
+// the highlight is the first colour in the rtf colour table
+const RTF_HEADER: &str = "{\\rtf1\\ansi\\ansicpg1252\\uc1\\deff0{\\fonttbl{\\f0\\fswiss Calibri;}}\
+{\\colortbl;\\red255\\green255\\blue0;}\n";
+
+// the runs of `para` between characters `start` and `end`
+fn slice_para(para: &Para, start: usize, end: usize) -> Para {
+    let mut sliced = Para {
+        index: para.index,
+        outline_level: para.outline_level,
+        runs: Vec::new(),
+    };
+    let mut position = 0;
+    for run in para.runs.iter() {
+        let len = run.text.chars().count();
+        let (from, to) = (start.max(position), end.min(position + len));
+        if from < to {
+            sliced.push_run(Run {
+                text: run
+                    .text
+                    .chars()
+                    .skip(from - position)
+                    .take(to - from)
+                    .collect(),
+                style: run.style.clone(),
+            });
+        }
+        position += len;
+    }
+    sliced
+}
+
+fn escape_rtf(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\t' => escaped.push_str("\\tab "),
+            '\n' => escaped.push_str("\\line "),
+            '\r' => (),
+            ' '..='~' => escaped.push(c),
+            // everything else as utf-16, with a ? for readers without unicode
+            _ => {
+                let mut units = [0; 2];
+                for unit in c.encode_utf16(&mut units) {
+                    let _ = write!(escaped, "\\u{}?", *unit as i16);
+                }
+            }
+        }
+    }
+    escaped
+}
+
+fn push_rtf_para(rtf: &mut String, para: &Para) {
+    rtf.push_str("\\pard\\plain");
+    if let Some(level) = para.outline_level {
+        let _ = write!(rtf, "\\outlinelevel{}", level);
+    }
+    for run in para.runs.iter() {
+        let style = &run.style;
+        rtf.push('{');
+        let controls_start = rtf.len();
+        for (on, control) in [
+            (style.bold, "\\b"),
+            (style.underline, "\\ul"),
+            (style.highlight, "\\highlight1"),
+        ] {
+            if on == Some(true) {
+                rtf.push_str(control);
+            }
+        }
+        if let Some(size) = style.size {
+            let _ = write!(rtf, "\\fs{}", size);
+        }
+        // a space ends the last control word
+        if rtf.len() > controls_start {
+            rtf.push(' ');
+        }
+        rtf.push_str(&escape_rtf(&run.text));
+        rtf.push('}');
+    }
+    rtf.push_str("\\par\n");
+}
+
+impl Clipboard {
+    /// The html as Windows' "HTML Format" clipboard format wants it: in a
+    /// document, after a header of byte offsets to the document and to the
+    /// fragment in it.
+    pub fn windows_html(&self) -> String {
+        let header =
+            |start_html: usize, end_html: usize, start_fragment: usize, end_fragment: usize| {
+                format!(
+                    "Version:0.9\r\nStartHTML:{:010}\r\nEndHTML:{:010}\r\n\
+StartFragment:{:010}\r\nEndFragment:{:010}\r\n",
+                    start_html, end_html, start_fragment, end_fragment
+                )
+            };
+        let prefix = "<html><body>\r\n<!--StartFragment-->";
+        let suffix = "<!--EndFragment-->\r\n</body></html>";
+        // the offsets are zero padded, so the header is always this long
+        let start_html = header(0, 0, 0, 0).len();
+        let start_fragment = start_html + prefix.len();
+        let end_fragment = start_fragment + self.html.len();
+        let end_html = end_fragment + suffix.len();
+        let mut html = header(start_html, end_html, start_fragment, end_fragment);
+        html.push_str(prefix);
+        html.push_str(&self.html);
+        html.push_str(suffix);
+        html
+    }
+}
+
+impl Export<'_> {
+    /// From character `start.1` of paragraph `start.0` up to, but not
+    /// including, character `end.1` of paragraph `end.0`. Paragraphs are
+    /// indexes in [`Document::paras`](crate::Document::paras), and offsets
+    /// past the end of a paragraph mean its end.
+    ///
+    /// `None` if either paragraph isn't exported, or `end` is before `start`.
+    pub fn clipboard(&self, start: (usize, usize), end: (usize, usize)) -> Option<Clipboard> {
+        if end < start {
+            return None;
+        }
+        let position = |index: usize| {
+            self.paras
+                .binary_search_by_key(&index, |para| para.index)
+                .ok()
+        };
+        let paras = &self.paras[position(start.0)?..=position(end.0)?];
+        let mut clipboard = Clipboard {
+            text: String::new(),
+            html: String::from("<meta charset=\"utf-8\">\n"),
+            rtf: String::from(RTF_HEADER),
+        };
+        for (i, para) in paras.iter().enumerate() {
+            let from = if i == 0 { start.1 } else { 0 };
+            let to = if i == paras.len() - 1 {
+                end.1
+            } else {
+                usize::MAX
+            };
+            let para = slice_para(para, from, to);
+            if i > 0 {
+                clipboard.text.push('\n');
+            }
+            clipboard.text.push_str(&para.text());
+            push_para(&mut clipboard.html, &para);
+            push_rtf_para(&mut clipboard.rtf, &para);
+        }
+        clipboard.rtf.push('}');
+        Some(clipboard)
+    }
+}
@@ -0,0 +1,262 @@
+use crate::document::{Document, LoadError};
+
+use std::io::{Cursor, Read};
+
+use aes::{Aes128, Aes192, Aes256};
+use cbc::cipher::block_padding::NoPadding;
+use cbc::cipher::{BlockDecryptMut, KeyIvInit};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+// word's "encrypt with password" stores the real docx as EncryptedPackage
+// inside an ole compound file, described by the xml in EncryptionInfo.
+// only agile encryption (MS-OFFCRYPTO 2.3.4.10), used since office 2010, is supported.
+
+pub const CFB_MAGIC: &[u8] = &[0xd0, 0xcf, 0x11, 0xe0, 0xa1, 0xb1, 0x1a, 0xe1];
+
+const AES_BLOCK_SIZE: usize = 16;
+// the most MS-OFFCRYPTO 2.3.4.10 allows, about a second of hashing
+const MAX_SPIN_COUNT: u32 = 10_000_000;
+
+// block keys from MS-OFFCRYPTO 2.3.4.13
+const VERIFIER_INPUT_BLOCK: &[u8] = &[0xfe, 0xa7, 0xd2, 0x76, 0x3b, 0x4b, 0x9e, 0x79];
+const VERIFIER_HASH_BLOCK: &[u8] = &[0xd7, 0xaa, 0x0f, 0x6d, 0x30, 0x61, 0x34, 0x4e];
+const KEY_VALUE_BLOCK: &[u8] = &[0x14, 0x6e, 0x0b, 0xe7, 0xab, 0xac, 0xd0, 0xd6];
+
+const SEGMENT_LENGTH: usize = 4096;
+
+#[derive(Default)]
+struct KeyParams {
+    salt: Vec<u8>,
+    key_bits: usize,
+    block_size: usize,
+    hash_algorithm: String,
+}
+
+#[derive(Default)]
+struct PasswordKey {
+    params: KeyParams,
+    spin_count: u32,
+    encrypted_verifier_hash_input: Vec<u8>,
+    encrypted_verifier_hash_value: Vec<u8>,
+    encrypted_key_value: Vec<u8>,
+}
+
+fn hash(algorithm: &str, parts: &[&[u8]]) -> Result<Vec<u8>, LoadError> {
+    fn digest<D: Digest>(parts: &[&[u8]]) -> Vec<u8> {
+        let mut hasher = D::new();
+        for part in parts {
+            hasher.update(part);
+        }
+        hasher.finalize().to_vec()
+    }
+    match algorithm {
+        "SHA1" => Ok(digest::<Sha1>(parts)),
+        "SHA256" => Ok(digest::<Sha256>(parts)),
+        "SHA384" => Ok(digest::<Sha384>(parts)),
+        "SHA512" => Ok(digest::<Sha512>(parts)),
+        _ => Err(LoadError::Encryption(format!(
+            "unsupported hash algorithm {}",
+            algorithm
+        ))),
+    }
+}
+
+// pad with 0x36 or truncate to the wanted length, MS-OFFCRYPTO 2.3.4.12
+fn fit(mut bytes: Vec<u8>, len: usize) -> Vec<u8> {
+    bytes.resize(len, 0x36);
+    bytes
+}
+
+fn decrypt(key: &[u8], iv: &[u8], data: &[u8]) -> Result<Vec<u8>, LoadError> {
+    fn run<C: KeyIvInit + BlockDecryptMut>(key: &[u8], iv: &[u8], data: &[u8]) -> Option<Vec<u8>> {
+        let mut buf = data.to_vec();
+        let len = C::new_from_slices(key, iv)
+            .ok()?
+            .decrypt_padded_mut::<NoPadding>(&mut buf)
+            .ok()?
+            .len();
+        buf.truncate(len);
+        Some(buf)
+    }
+    let decrypted = match key.len() {
+        16 => run::<cbc::Decryptor<Aes128>>(key, iv, data),
+        24 => run::<cbc::Decryptor<Aes192>>(key, iv, data),
+        32 => run::<cbc::Decryptor<Aes256>>(key, iv, data),
+        _ => None,
+    };
+    decrypted.ok_or_else(|| LoadError::Encryption("couldn't decrypt".to_string()))
+}
+
+fn read_key_params(reader: &Reader<&[u8]>, e: &quick_xml::events::BytesStart) -> KeyParams {
+    let get = |name: &[u8]| Document::get_attr(reader, e, name).unwrap_or_default();
+    KeyParams {
+        salt: base64::decode(get(b"saltValue")).unwrap_or_default(),
+        key_bits: get(b"keyBits").parse().unwrap_or(0),
+        block_size: get(b"blockSize").parse().unwrap_or(16),
+        hash_algorithm: get(b"hashAlgorithm"),
+    }
+}
+
+fn read_encryption_info(info: &[u8]) -> Result<(KeyParams, PasswordKey), LoadError> {
+    // version 4.4 is agile, 3.2 and 4.2 are the older standard encryption
+    if info.len() < 8 || info[0..4] != [4, 0, 4, 0] {
+        return Err(LoadError::Encryption(
+            "only agile encryption is supported".to_string(),
+        ));
+    }
+    let xml = String::from_utf8_lossy(&info[8..]);
+    let mut reader = Reader::from_str(&xml);
+    let mut buf = Vec::new();
+    let mut key_data = None;
+    let mut password_key = None;
+    loop {
+        match reader.read_event(&mut buf) {
+            Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) => match e.local_name() {
+                b"keyData" => key_data = Some(read_key_params(&reader, e)),
+                b"encryptedKey" => {
+                    let get =
+                        |name: &[u8]| Document::get_attr(&reader, e, name).unwrap_or_default();
+                    password_key = Some(PasswordKey {
+                        params: read_key_params(&reader, e),
+                        spin_count: get(b"spinCount").parse().unwrap_or(0),
+                        encrypted_verifier_hash_input: base64::decode(get(
+                            b"encryptedVerifierHashInput",
+                        ))
+                        .unwrap_or_default(),
+                        encrypted_verifier_hash_value: base64::decode(get(
+                            b"encryptedVerifierHashValue",
+                        ))
+                        .unwrap_or_default(),
+                        encrypted_key_value: base64::decode(get(b"encryptedKeyValue"))
+                            .unwrap_or_default(),
+                    });
+                }
+                _ => (),
+            },
+            Ok(Event::Eof) => break,
+            Err(e) => {
+                return Err(LoadError::Encryption(format!(
+                    "bad EncryptionInfo: {:?}",
+                    e
+                )))
+            }
+            _ => (),
+        }
+        buf.clear();
+    }
+    match (key_data, password_key) {
+        (Some(key_data), Some(password_key)) => {
+            check_key_params(&key_data)?;
+            check_key_params(&password_key.params)?;
+            if password_key.spin_count > MAX_SPIN_COUNT {
+                return Err(LoadError::Encryption("spin count is too large".to_string()));
+            }
+            Ok((key_data, password_key))
+        }
+        _ => Err(LoadError::Encryption(
+            "no password key in EncryptionInfo".to_string(),
+        )),
+    }
+}
+
+// sizes come straight from the file, so check them before allocating keys and ivs
+fn check_key_params(params: &KeyParams) -> Result<(), LoadError> {
+    if !matches!(params.key_bits, 128 | 192 | 256) || params.block_size != AES_BLOCK_SIZE {
+        return Err(LoadError::Encryption(format!(
+            "unsupported key size {} or block size {}",
+            params.key_bits, params.block_size
+        )));
+    }
+    Ok(())
+}
+
+// get the package's secret key from the password, checking it against the verifier
+fn secret_key(key: &PasswordKey, password: &str) -> Result<Vec<u8>, LoadError> {
+    let params = &key.params;
+    let algorithm = params.hash_algorithm.as_str();
+    let password: Vec<u8> = password
+        .encode_utf16()
+        .flat_map(|unit| unit.to_le_bytes())
+        .collect();
+    let mut h = hash(algorithm, &[&params.salt, &password])?;
+    for i in 0..key.spin_count {
+        h = hash(algorithm, &[&i.to_le_bytes(), &h])?;
+    }
+    let key_len = params.key_bits / 8;
+    let derive = |block: &[u8]| hash(algorithm, &[&h, block]).map(|h| fit(h, key_len));
+
+    let verifier_input = decrypt(
+        &derive(VERIFIER_INPUT_BLOCK)?,
+        &params.salt,
+        &key.encrypted_verifier_hash_input,
+    )?;
+    let verifier_hash = decrypt(
+        &derive(VERIFIER_HASH_BLOCK)?,
+        &params.salt,
+        &key.encrypted_verifier_hash_value,
+    )?;
+    let expected = hash(
+        algorithm,
+        &[&verifier_input[..params.salt.len().min(verifier_input.len())]],
+    )?;
+    if verifier_hash.len() < expected.len() || verifier_hash[..expected.len()] != expected[..] {
+        return Err(LoadError::WrongPassword);
+    }
+    let secret = decrypt(
+        &derive(KEY_VALUE_BLOCK)?,
+        &params.salt,
+        &key.encrypted_key_value,
+    )?;
+    Ok(secret[..key_len.min(secret.len())].to_vec())
+}
+
+fn decrypt_package(
+    key_data: &KeyParams,
+    secret: &[u8],
+    package: &[u8],
+) -> Result<Vec<u8>, LoadError> {
+    if package.len() < 8 {
+        return Err(LoadError::Encryption(
+            "EncryptedPackage is too short".to_string(),
+        ));
+    }
+    let mut size = [0; 8];
+    size.copy_from_slice(&package[..8]);
+    let size = u64::from_le_bytes(size) as usize;
+    let mut decrypted = Vec::with_capacity(package.len());
+    // every 4096 byte segment has its own iv, from the salt and segment number
+    for (i, segment) in package[8..].chunks(SEGMENT_LENGTH).enumerate() {
+        let iv = fit(
+            hash(
+                &key_data.hash_algorithm,
+                &[&key_data.salt, &(i as u32).to_le_bytes()],
+            )?,
+            key_data.block_size,
+        );
+        // segments are padded to the block size, a truncated file isn't
+        let len = segment.len() - segment.len() % key_data.block_size.max(1);
+        decrypted.extend(decrypt(secret, &iv, &segment[..len])?);
+    }
+    decrypted.truncate(size);
+    Ok(decrypted)
+}
+
+pub fn decrypt_docx(bytes: &[u8], password: &str) -> Result<Vec<u8>, LoadError> {
+    let bad_container =
+        |e: std::io::Error| LoadError::Encryption(format!("bad compound file: {}", e));
+    let mut file = cfb::CompoundFile::open(Cursor::new(bytes)).map_err(bad_container)?;
+    let mut info = Vec::new();
+    file.open_stream("/EncryptionInfo")
+        .and_then(|mut stream| stream.read_to_end(&mut info))
+        .map_err(bad_container)?;
+    let mut package = Vec::new();
+    file.open_stream("/EncryptedPackage")
+        .and_then(|mut stream| stream.read_to_end(&mut package))
+        .map_err(bad_container)?;
+    let (key_data, password_key) = read_encryption_info(&info)?;
+    let secret = secret_key(&password_key, password)?;
+    decrypt_package(&key_data, &secret, &package)
+}
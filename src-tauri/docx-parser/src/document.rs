@@ -0,0 +1,511 @@
+use crate::crypto::{decrypt_docx, CFB_MAGIC};
+use crate::metadata::DocumentInfo;
+use crate::package::Package;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::str;
+
+use serde::{Deserialize, Serialize};
+
+use quick_xml::events::BytesStart;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+/// Character formatting. `None` means the property wasn't set, so it's
+/// inherited from the paragraph or style underneath.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Style {
+    pub bold: Option<bool>,
+    pub underline: Option<bool>,
+    pub highlight: Option<bool>,
+    /// Font size in half points, like `w:sz`.
+    pub size: Option<u32>,
+}
+/// A named style from the document's style sheet.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Attr {
+    pub style: Style,
+    pub outline_level: Option<u32>,
+}
+/// A span of text with the same formatting.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Run {
+    pub text: String,
+    pub style: Style,
+}
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Para {
+    /// Position in [`Document::paras`].
+    pub index: usize,
+    /// Heading level, starting at 0 for the top level. `None` for body text.
+    pub outline_level: Option<u32>,
+    pub runs: Vec<Run>,
+}
+impl Para {
+    /// The text of every run, without formatting.
+    pub fn text(&self) -> String {
+        self.runs.iter().map(|run| run.text.as_str()).collect()
+    }
+    /// Appends a run, merging it into the last one if they're formatted the same.
+    pub fn push_run(&mut self, run: Run) {
+        // if run exists
+        if run.text.is_empty() {
+            return;
+        }
+        // if last run style is the same, merge them
+        match self.runs.last_mut() {
+            Some(last_run) if last_run.style == run.style => last_run.text.push_str(&run.text),
+            _ => self.runs.push(run),
+        }
+    }
+}
+/// A heading, copied from [`Document::paras`] for the outline.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OutlinePara {
+    /// Position in [`Document::outline_paras`].
+    pub index: usize,
+    /// Index of the paragraph it was copied from.
+    pub link: usize,
+    pub outline_level: Option<u32>,
+    pub runs: Vec<Run>,
+}
+/// Why a document couldn't be opened at all. Damage that still leaves
+/// something to show is reported in [`Document::warnings`] instead.
+#[derive(Debug)]
+pub enum LoadError {
+    /// The file is encrypted and no password was given.
+    PasswordRequired,
+    WrongPassword,
+    /// The file is encrypted in a way that isn't supported, or is damaged.
+    Encryption(String),
+    Io(io::Error),
+}
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::PasswordRequired => write!(f, "password required"),
+            LoadError::WrongPassword => write!(f, "wrong password"),
+            LoadError::Encryption(message) => write!(f, "couldn't decrypt file: {}", message),
+            LoadError::Io(e) => write!(f, "couldn't read file: {}", e),
+        }
+    }
+}
+impl std::error::Error for LoadError {}
+impl From<io::Error> for LoadError {
+    fn from(e: io::Error) -> LoadError {
+        LoadError::Io(e)
+    }
+}
+
+/// Bumped whenever parser output changes, so cached documents get reparsed.
+pub const PARSER_VERSION: u32 = 6;
+
+/// A parsed document. Create one with [`Document::new`], then call one of the
+/// `load_` methods.
+#[derive(Serialize, Deserialize)]
+pub struct Document {
+    pub paras: Vec<Para>,
+    /// Every paragraph with an outline level, in order.
+    pub outline_paras: Vec<OutlinePara>,
+    /// Styles by id, with their `basedOn` chain already applied.
+    pub style_map: HashMap<String, Attr>,
+    pub info: DocumentInfo,
+    /// What couldn't be read from a damaged file.
+    pub warnings: Vec<String>,
+}
+impl Default for Document {
+    fn default() -> Document {
+        Document::new()
+    }
+}
+impl Document {
+    pub fn new() -> Document {
+        Document {
+            outline_paras: Vec::new(),
+            paras: Vec::new(),
+            style_map: HashMap::new(),
+            info: DocumentInfo::default(),
+            warnings: Vec::new(),
+        }
+    }
+    pub fn load_file(&mut self, file_path: &str) -> Result<(), LoadError> {
+        let bytes = fs::read(file_path)?;
+        self.load_bytes(&bytes)
+    }
+    /// Loads a document in any supported format.
+    pub fn load_bytes(&mut self, bytes: &[u8]) -> Result<(), LoadError> {
+        self.load_bytes_with_password(bytes, None)
+    }
+    /// Like [`Document::load_bytes`], decrypting password protected docx
+    /// files with `password`.
+    pub fn load_bytes_with_password(
+        &mut self,
+        bytes: &[u8],
+        password: Option<&str>,
+    ) -> Result<(), LoadError> {
+        // password protected docx files are ole compound files, not zips
+        if bytes.starts_with(CFB_MAGIC) {
+            let password = password.ok_or(LoadError::PasswordRequired)?;
+            let decrypted = decrypt_docx(bytes, password)?;
+            self.load_zip(&decrypted);
+            return Ok(());
+        }
+        // skip a utf-8 byte order mark and leading whitespace to find the format
+        let start = bytes.strip_prefix(b"\xef\xbb\xbf").unwrap_or(bytes);
+        let start = &start[start
+            .iter()
+            .position(|byte| !byte.is_ascii_whitespace())
+            .unwrap_or(start.len())..];
+        if start.starts_with(b"{\\rtf") {
+            self.load_rtf(bytes);
+        } else if start.starts_with(b"<") {
+            self.load_flat_xml(&String::from_utf8_lossy(start));
+        } else {
+            self.load_zip(bytes);
+        }
+        Ok(())
+    }
+    /// Loads a zipped docx or odt package.
+    pub fn load_zip(&mut self, bytes: &[u8]) {
+        let mut package = Package::open(bytes, &mut self.warnings);
+        self.load_doc_props(&mut package);
+        // opendocument packages are zips too, tell them apart by their parts
+        if !package.has_part("word/document.xml") && package.has_part("content.xml") {
+            self.load_odt(&mut package);
+            return;
+        }
+        match package.read_part("word/styles.xml", &mut self.warnings) {
+            Some(styles) => self.load_style_map(&styles),
+            None => self
+                .warnings
+                .push("word/styles.xml is missing, formatting was lost".to_string()),
+        }
+        match package.read_part("word/document.xml", &mut self.warnings) {
+            Some(document) => self.load_paras(&document),
+            None => self
+                .warnings
+                .push("word/document.xml is missing, the text was lost".to_string()),
+        }
+    }
+    pub(crate) fn get_attr(
+        reader: &Reader<&[u8]>,
+        event: &BytesStart,
+        attr_name: &[u8],
+    ) -> Option<String> {
+        let mut attr_val = None;
+        // skip malformed attributes instead of giving up on the element
+        for attribute in event.attributes().flatten() {
+            if attribute.key == attr_name {
+                // an unknown entity keeps the value as written
+                attr_val = Some(
+                    attribute
+                        .unescape_and_decode_value(reader)
+                        .unwrap_or_else(|_| String::from_utf8_lossy(&attribute.value).into_owned()),
+                );
+            }
+        }
+        attr_val
+    }
+    // w:outlineLvl, where 9 means body text
+    pub(crate) fn parse_outline_level(level: &str) -> Option<u32> {
+        level.parse::<u32>().ok().filter(|level| *level < 9)
+    }
+    /// Loads styles from the contents of word/styles.xml. Call this before
+    /// [`Document::load_paras`], so paragraphs can refer to the styles.
+    pub fn load_style_map(&mut self, contents: &str) {
+        let mut reader = Reader::from_str(contents);
+
+        let mut buf = Vec::new();
+        let mut path: Vec<Vec<u8>> = Vec::new();
+        let mut current_attr = Attr {
+            style: Style {
+                bold: None,
+                underline: None,
+                highlight: None,
+                size: None,
+            },
+            outline_level: None,
+        };
+        let mut current_style_id: Option<String> = None;
+        loop {
+            match reader.read_event(&mut buf) {
+                Ok(Event::Start(ref e)) => {
+                    let name = e.name().to_vec();
+                    path.push(name);
+                    if e.name() == b"w:style" {
+                        current_style_id = Self::get_attr(&reader, e, b"w:styleId");
+                    }
+                }
+                Ok(Event::Empty(ref e)) => {
+                    match e.name() {
+                        b"w:b" => {
+                            let bold = Self::get_attr(&reader, e, b"w:val");
+                            if bold.unwrap_or("1".to_string()) != "0" {
+                                current_attr.style.bold = Some(true);
+                            }
+                        }
+                        b"w:u" => {
+                            let underline = Self::get_attr(&reader, e, b"w:val");
+                            if underline.unwrap_or("single".to_string()) != "none" {
+                                current_attr.style.underline = Some(true);
+                            }
+                        }
+                        b"w:highlight" => {
+                            let highlight = Self::get_attr(&reader, e, b"w:val");
+                            if highlight.is_some() {
+                                current_attr.style.highlight = Some(true);
+                            }
+                        }
+                        b"w:sz" => {
+                            if let Some(size) = Self::get_attr(&reader, e, b"w:val") {
+                                current_attr.style.size = size.parse::<u32>().ok();
+                            }
+                        }
+                        b"w:outlineLvl" => {
+                            if let Some(outline_level) = Self::get_attr(&reader, e, b"w:val") {
+                                current_attr.outline_level =
+                                    Self::parse_outline_level(&outline_level);
+                            }
+                        }
+                        b"w:basedOn" => {
+                            if let Some(id) = Self::get_attr(&reader, e, b"w:val") {
+                                self.get_style_id(&mut current_attr.style, &id);
+                            }
+                        }
+                        _ => (),
+                    };
+                }
+                Ok(Event::End(_e)) => {
+                    let end_tag = path.pop().unwrap_or_default();
+                    if end_tag == b"w:style" && path.len() == 1 {
+                        // a style without an id can't be referred to
+                        if let Some(id) = current_style_id.clone() {
+                            self.style_map.insert(id, current_attr.clone());
+                        }
+
+                        current_attr = Attr {
+                            style: Style {
+                                bold: None,
+                                underline: None,
+                                highlight: None,
+                                size: None,
+                            },
+                            outline_level: None,
+                        };
+                        current_style_id = None;
+                    }
+                }
+                Ok(Event::Eof) => break,
+                Err(e) => {
+                    // keep the styles read so far
+                    self.warnings.push(format!(
+                        "styles are damaged at position {} ({:?}), later styles were lost",
+                        reader.buffer_position(),
+                        e
+                    ));
+                    break;
+                }
+                _ => (),
+            }
+            buf.clear();
+        }
+        // for key in &self.style_map {
+        //   println!("{:?}", key.0);
+        //   if key.1.bold.unwrap_or(false) {
+        //     println!("bold")
+        //   }
+        //   if key.1.underline.unwrap_or(false) {
+        //     println!("underline")
+        //   }
+        //   if key.1.highlight.unwrap_or(false) {
+        //     println!("highlight")
+        //   }
+        //   if key.1.size.is_some() {
+        //     println!("{:?}", key.1.size.unwrap());
+        //   }
+        // }
+    }
+    /// Loads paragraphs from the contents of word/document.xml.
+    pub fn load_paras(&mut self, contents: &str) {
+        let mut reader = Reader::from_str(contents);
+
+        let mut buf = Vec::new();
+        let mut path: Vec<Vec<u8>> = Vec::new();
+        let mut current_para = Para {
+            index: 0,
+            runs: Vec::new(),
+            outline_level: None,
+        };
+        let mut current_run = Run {
+            text: "".to_string(),
+            style: Style {
+                bold: None,
+                underline: None,
+                highlight: None,
+                size: None,
+            },
+        };
+
+        let mut current_para_style = Style {
+            bold: None,
+            underline: None,
+            highlight: None,
+            size: None,
+        };
+        self.get_style_id(&mut current_para_style, &"Normal".to_string());
+        let mut current_run_style: Style;
+        let mut current_style = &mut current_para_style;
+        loop {
+            match reader.read_event(&mut buf) {
+                Ok(Event::Start(ref e)) => {
+                    let name = e.name().to_vec();
+                    path.push(name);
+                    match e.name() {
+                        b"w:p" => {
+                            current_style = &mut current_para_style;
+                        }
+                        b"w:r" => {
+                            current_run_style = current_para_style.clone();
+                            current_style = &mut current_run_style;
+                        }
+                        _ => (),
+                    }
+                }
+                Ok(Event::Empty(ref e)) => {
+                    match e.name() {
+                        b"w:b" => {
+                            let bold = Self::get_attr(&reader, e, b"w:val");
+                            current_style.bold = Some(bold.unwrap_or("1".to_string()) != "0")
+                        }
+                        b"w:u" => {
+                            let underline = Self::get_attr(&reader, e, b"w:val");
+                            current_style.underline =
+                                Some(underline.unwrap_or("single".to_string()) != "none")
+                        }
+                        b"w:highlight" => {
+                            let highlight = Self::get_attr(&reader, e, b"w:val");
+                            if highlight.is_some() {
+                                current_style.highlight = Some(true);
+                            }
+                        }
+                        b"w:sz" => {
+                            if let Some(size) = Self::get_attr(&reader, e, b"w:val") {
+                                current_style.size = size.parse::<u32>().ok();
+                            }
+                        }
+                        b"w:rStyle" => {
+                            if let Some(id) = Self::get_attr(&reader, e, b"w:val") {
+                                self.get_style_id(current_style, &id);
+                            }
+                        }
+                        b"w:pStyle" => {
+                            if let Some(id) = Self::get_attr(&reader, e, b"w:val") {
+                                let attr = self.style_map.get(&id);
+                                if let Some(outline_level) =
+                                    attr.and_then(|attr| attr.outline_level)
+                                {
+                                    current_para.outline_level = Some(outline_level);
+                                }
+                                self.get_style_id(current_style, &id);
+                            }
+                        }
+                        b"w:outlineLvl" => {
+                            if let Some(outline_level) = Self::get_attr(&reader, e, b"w:val") {
+                                current_para.outline_level =
+                                    Self::parse_outline_level(&outline_level);
+                            }
+                        }
+                        _ => (),
+                    };
+                }
+                Ok(Event::End(_e)) => {
+                    let end_tag = path.pop().unwrap_or_default();
+                    if end_tag == b"w:r" {
+                        // format run text
+                        // replace whitespace
+                        current_run.text = current_run.text.replace("\n", "");
+                        current_run.text = current_run.text.replace("\r", "");
+                        current_run.text = current_run.text.replace("\t", "");
+
+                        current_run.style = current_style.clone();
+                        current_para.push_run(current_run.clone());
+                        current_style.bold = None;
+                        current_style.underline = None;
+                        current_style.highlight = None;
+                        current_style.size = None;
+                        current_style = &mut current_para_style;
+                        current_run = Run {
+                            text: "".to_string(),
+                            style: Style {
+                                bold: None,
+                                underline: None,
+                                highlight: None,
+                                size: None,
+                            },
+                        };
+                    } else if end_tag == b"w:p" {
+                        self.push_para(current_para);
+
+                        current_style.bold = None;
+                        current_style.underline = None;
+                        current_style.highlight = None;
+                        current_style.size = None;
+                        self.get_style_id(current_style, &"Normal".to_string());
+                        current_para = Para {
+                            index: 0,
+                            runs: Vec::new(),
+                            outline_level: None,
+                        };
+                    }
+                }
+                // word 2003 xml keeps images inline as base64 text
+                Ok(Event::Text(_)) if path.last().map_or(false, |tag| tag == b"w:binData") => (),
+                Ok(Event::Text(e)) => {
+                    current_run.text += &e
+                        .unescape_and_decode(&reader)
+                        .unwrap_or_else(|_| String::from_utf8_lossy(&e).into_owned());
+                }
+                Ok(Event::Eof) => break,
+                Err(e) => {
+                    // keep every paragraph before the error
+                    self.warnings.push(format!(
+                        "document is damaged at position {} ({:?}), paragraphs after paragraph {} were lost",
+                        reader.buffer_position(),
+                        e,
+                        self.paras.len()
+                    ));
+                    break;
+                }
+                _ => (),
+            }
+            buf.clear();
+        }
+    }
+    pub(crate) fn push_para(&mut self, mut para: Para) {
+        para.index = self.paras.len();
+        if para.outline_level.is_some() {
+            self.outline_paras.push(OutlinePara {
+                index: self.outline_paras.len(),
+                link: para.index,
+                outline_level: para.outline_level,
+                runs: para.runs.clone(),
+            });
+        }
+        self.paras.push(para);
+    }
+    pub(crate) fn get_style_id(&mut self, style: &mut Style, style_id: &String) {
+        if let Some(id_style) = self.style_map.get(style_id) {
+            Self::mutate_style(style, &id_style.style);
+        }
+    }
+    // pub fn get_style(&mut self, style: &mut Style, element: &Element) {}
+    pub(crate) fn mutate_style(style: &mut Style, priority_style: &Style) {
+        style.bold = priority_style.bold.or(style.bold);
+        style.underline = priority_style.underline.or(style.underline);
+        style.highlight = priority_style.highlight.or(style.highlight);
+        style.size = priority_style.size.or(style.size);
+    }
+}
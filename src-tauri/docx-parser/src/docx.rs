@@ -0,0 +1,1031 @@
+use crate::crypto::CFB_MAGIC;
+use crate::document::Document;
+use crate::export::Export;
+use crate::package::Package;
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{self, Cursor, Write};
+use std::ops::{Range, RangeInclusive};
+
+use quick_xml::escape::escape;
+use quick_xml::events::attributes::Attribute;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::{Reader, Writer};
+use zip::result::ZipError;
+use zip::write::{FileOptions, ZipWriter};
+use zip::CompressionMethod;
+
+// paragraphs are copied with their xml exactly as it was written, into a
+// package that has everything else word needs to show them the same way. the
+// first document is the base: parts the whole document uses, like styles,
+// numbering, settings and the theme, are copied from it unchanged, and parts
+// only paragraphs point to, like images, links, charts and headers, come along
+// if a copied paragraph points to them.
+//
+// paragraphs from other documents are merged into the base. the parts they
+// point to are copied under names that don't clash, styles the base doesn't
+// have are added, and their lists are renumbered after the base's. footnotes,
+// endnotes and comments only come from the base, so references to them are
+// dropped from the others.
+
+const DOCUMENT: &str = "word/document.xml";
+const DOCUMENT_RELS: &str = "word/_rels/document.xml.rels";
+const STYLES: &str = "word/styles.xml";
+const NUMBERING: &str = "word/numbering.xml";
+const PACKAGE_RELS: &str = "_rels/.rels";
+const CONTENT_TYPES: &str = "[Content_Types].xml";
+
+const NUMBERING_TYPE: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/numbering";
+const NUMBERING_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-officedocument.wordprocessingml.numbering+xml";
+const RELS_CONTENT_TYPE: &str = "application/vnd.openxmlformats-package.relationships+xml";
+
+// references to parts of the base that other documents' paragraphs can't use
+const NOTE_REFERENCES: &[&[u8]] = &[
+    b"w:footnoteReference",
+    b"w:endnoteReference",
+    b"w:commentReference",
+    b"w:commentRangeStart",
+    b"w:commentRangeEnd",
+];
+
+/// Why paragraphs couldn't be copied into a new docx.
+#[derive(Debug)]
+pub enum WriteError {
+    /// A source is password protected.
+    Encrypted,
+    /// A source isn't a zipped docx, e.g. it's rtf or odt.
+    NotDocx,
+    /// A part the new docx needs is missing or can't be read.
+    Damaged(String),
+    /// A paragraph isn't in the source, which happens when the file changed
+    /// after it was loaded.
+    MissingParagraph(usize),
+    Io(io::Error),
+}
+impl fmt::Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WriteError::Encrypted => write!(f, "can't copy from a password protected file"),
+            WriteError::NotDocx => write!(f, "can only copy from docx files"),
+            WriteError::Damaged(message) => write!(f, "the file is damaged: {}", message),
+            WriteError::MissingParagraph(index) => write!(
+                f,
+                "paragraph {} isn't in the file, it may have changed",
+                index
+            ),
+            WriteError::Io(e) => write!(f, "couldn't write file: {}", e),
+        }
+    }
+}
+impl std::error::Error for WriteError {}
+impl From<io::Error> for WriteError {
+    fn from(e: io::Error) -> WriteError {
+        WriteError::Io(e)
+    }
+}
+impl From<ZipError> for WriteError {
+    fn from(e: ZipError) -> WriteError {
+        WriteError::Io(e.into())
+    }
+}
+
+// a child of w:body, and the paragraphs in it, numbered like Document::paras
+struct Block {
+    range: Range<usize>,
+    paras: Range<usize>,
+}
+
+// where things are in document.xml. the body's last w:sectPr holds the page
+// setup for the whole document, so it's kept apart to always be copied
+struct Body {
+    root: Range<usize>,
+    contents: Range<usize>,
+    blocks: Vec<Block>,
+    sect_pr: Option<Range<usize>>,
+}
+
+fn read_body(document: &str) -> Result<Body, WriteError> {
+    let mut reader = Reader::from_str(document);
+    let mut buf = Vec::new();
+    let mut depth = 0;
+    let mut root = 0..0;
+    let mut body_depth: Option<usize> = None;
+    let mut body_start = 0;
+    // the block being read, and the number of paragraphs before it
+    let mut block_start: Option<(usize, usize, bool)> = None;
+    let mut para_count = 0;
+    let mut blocks = Vec::new();
+    let mut sect_pr = None;
+    loop {
+        let position = reader.buffer_position();
+        match reader.read_event(&mut buf) {
+            Ok(Event::Start(ref e)) => {
+                if depth == 0 {
+                    root = position..reader.buffer_position();
+                }
+                if body_depth == Some(depth) {
+                    block_start = Some((position, para_count, e.name() == b"w:sectPr"));
+                }
+                depth += 1;
+                if body_depth.is_none() && e.name() == b"w:body" {
+                    body_depth = Some(depth);
+                    body_start = reader.buffer_position();
+                }
+            }
+            Ok(Event::Empty(ref e)) if body_depth == Some(depth) => {
+                let range = position..reader.buffer_position();
+                if e.name() == b"w:sectPr" {
+                    sect_pr = Some(range);
+                } else {
+                    blocks.push(Block {
+                        range,
+                        paras: para_count..para_count,
+                    });
+                }
+            }
+            Ok(Event::End(ref e)) => {
+                depth -= 1;
+                // counted the same way load_paras counts them
+                if e.name() == b"w:p" {
+                    para_count += 1;
+                }
+                if body_depth == Some(depth + 1) {
+                    return Ok(Body {
+                        root,
+                        contents: body_start..position,
+                        blocks,
+                        sect_pr,
+                    });
+                }
+                if body_depth == Some(depth) {
+                    if let Some((start, first_para, is_sect_pr)) = block_start.take() {
+                        let range = start..reader.buffer_position();
+                        if is_sect_pr {
+                            sect_pr = Some(range);
+                        } else {
+                            blocks.push(Block {
+                                range,
+                                paras: first_para..para_count,
+                            });
+                        }
+                    }
+                }
+            }
+            Ok(Event::Eof) => {
+                return Err(WriteError::Damaged(format!("{} has no body", DOCUMENT)));
+            }
+            Err(e) => {
+                return Err(WriteError::Damaged(format!(
+                    "{} can't be read at position {} ({:?})",
+                    DOCUMENT,
+                    reader.buffer_position(),
+                    e
+                )));
+            }
+            _ => (),
+        }
+        buf.clear();
+    }
+}
+
+// an element's name and where it is
+type Element = (Vec<u8>, Range<usize>);
+
+// the elements directly inside the root, and where the root's end tag starts
+fn read_children(xml: &str) -> (Vec<Element>, usize) {
+    let mut reader = Reader::from_str(xml);
+    let mut buf = Vec::new();
+    let mut depth = 0;
+    let mut children = Vec::new();
+    let mut child_start = 0;
+    loop {
+        let position = reader.buffer_position();
+        match reader.read_event(&mut buf) {
+            Ok(Event::Start(_)) => {
+                if depth == 1 {
+                    child_start = position;
+                }
+                depth += 1;
+            }
+            Ok(Event::Empty(ref e)) if depth == 1 => {
+                children.push((e.name().to_vec(), position..reader.buffer_position()));
+            }
+            Ok(Event::End(ref e)) => {
+                depth -= 1;
+                match depth {
+                    0 => return (children, position),
+                    1 => children.push((e.name().to_vec(), child_start..reader.buffer_position())),
+                    _ => (),
+                }
+            }
+            Ok(Event::Eof) | Err(_) => return (children, xml.len()),
+            _ => (),
+        }
+        buf.clear();
+    }
+}
+
+// an attribute of the element that starts `xml`
+fn first_attr(xml: &str, name: &[u8]) -> Option<String> {
+    let mut reader = Reader::from_str(xml);
+    let start = read_start(&mut reader)?;
+    Document::get_attr(&reader, &start, name)
+}
+
+// the first element's start tag, past any text, comments or declaration
+fn read_start(reader: &mut Reader<&[u8]>) -> Option<BytesStart<'static>> {
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event(&mut buf) {
+            Ok(Event::Start(e)) | Ok(Event::Empty(e)) => return Some(e.into_owned()),
+            Ok(Event::Eof) | Err(_) => return None,
+            _ => (),
+        }
+        buf.clear();
+    }
+}
+
+#[derive(Clone)]
+struct Relationship {
+    id: String,
+    kind: String,
+    target: String,
+    external: bool,
+}
+
+// the relationships in a .rels part, as far as they can be read
+fn read_relationships(xml: &str) -> Vec<Relationship> {
+    let mut reader = Reader::from_str(xml);
+    let mut buf = Vec::new();
+    let mut relationships = Vec::new();
+    loop {
+        match reader.read_event(&mut buf) {
+            Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) if e.name() == b"Relationship" => {
+                let attr = |name: &[u8]| Document::get_attr(&reader, e, name);
+                if let (Some(id), Some(target)) = (attr(b"Id"), attr(b"Target")) {
+                    relationships.push(Relationship {
+                        id,
+                        kind: attr(b"Type").unwrap_or_default(),
+                        target,
+                        external: attr(b"TargetMode").as_deref() == Some("External"),
+                    });
+                }
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => (),
+        }
+        buf.clear();
+    }
+    relationships
+}
+
+fn write_relationships(relationships: &[Relationship]) -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+         <Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">",
+    );
+    for relationship in relationships {
+        xml.push_str(&format!(
+            r#"<Relationship Id="{}" Type="{}" Target="{}"{}/>"#,
+            escape_str(&relationship.id),
+            escape_str(&relationship.kind),
+            escape_str(&relationship.target),
+            if relationship.external {
+                r#" TargetMode="External""#
+            } else {
+                ""
+            }
+        ));
+    }
+    xml.push_str("</Relationships>");
+    xml
+}
+
+fn escape_str(text: &str) -> String {
+    String::from_utf8_lossy(&escape(text.as_bytes())).into_owned()
+}
+
+// every attribute value in the xml. relationship ids are only ever attribute
+// values, and anything else that happens to match one just keeps an extra part
+fn attribute_values(xml: &str) -> HashSet<String> {
+    let mut reader = Reader::from_str(xml);
+    let mut buf = Vec::new();
+    let mut values = HashSet::new();
+    loop {
+        match reader.read_event(&mut buf) {
+            Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) => {
+                for attribute in e.attributes().flatten() {
+                    values.insert(String::from_utf8_lossy(&attribute.value).into_owned());
+                }
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => (),
+        }
+        buf.clear();
+    }
+    values
+}
+
+// word/document.xml's relationships are in word/_rels/document.xml.rels, the
+// package's in _rels/.rels
+fn rels_name(part: &str) -> String {
+    match part.rfind('/') {
+        Some(slash) => format!("{}/_rels/{}.rels", &part[..slash], &part[slash + 1..]),
+        None => format!("_rels/{}.rels", part),
+    }
+}
+
+// the part a relationship's target points to, relative to the part that has
+// the relationship unless it starts with a slash
+fn resolve_target(source: &str, target: &str) -> String {
+    let path = match target.strip_prefix('/') {
+        Some(absolute) => absolute.to_string(),
+        None => match source.rfind('/') {
+            Some(slash) => format!("{}/{}", &source[..slash], target),
+            None => target.to_string(),
+        },
+    };
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => (),
+            ".." => {
+                segments.pop();
+            }
+            _ => segments.push(segment),
+        }
+    }
+    segments.join("/")
+}
+
+// [Content_Types].xml, which gives the type of each part by its extension or
+// its name
+#[derive(Default)]
+struct ContentTypes {
+    defaults: Vec<(String, String)>,
+    overrides: Vec<(String, String)>,
+}
+impl ContentTypes {
+    fn read(xml: &str) -> ContentTypes {
+        let mut content_types = ContentTypes::default();
+        let mut reader = Reader::from_str(xml);
+        let mut buf = Vec::new();
+        loop {
+            match reader.read_event(&mut buf) {
+                Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) => {
+                    let attr = |name: &[u8]| Document::get_attr(&reader, e, name);
+                    let content_type = attr(b"ContentType").unwrap_or_default();
+                    match (e.name(), attr(b"Extension"), attr(b"PartName")) {
+                        (b"Default", Some(extension), _) => {
+                            content_types.defaults.push((extension, content_type))
+                        }
+                        (b"Override", _, Some(part_name)) => content_types
+                            .overrides
+                            .push((part_name.trim_start_matches('/').to_string(), content_type)),
+                        _ => (),
+                    }
+                }
+                Ok(Event::Eof) | Err(_) => break,
+                _ => (),
+            }
+            buf.clear();
+        }
+        content_types
+    }
+    fn of(&self, part: &str) -> Option<&str> {
+        let extension = part.rsplit('.').next().unwrap_or_default();
+        self.overrides
+            .iter()
+            .find(|(name, _)| name == part)
+            .or_else(|| {
+                self.defaults
+                    .iter()
+                    .find(|(other, _)| other.eq_ignore_ascii_case(extension))
+            })
+            .map(|(_, content_type)| content_type.as_str())
+    }
+    // without overrides for parts that were left out
+    fn write(&self, kept: &HashSet<String>) -> String {
+        let mut xml = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+             <Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">",
+        );
+        for (extension, content_type) in self.defaults.iter() {
+            xml.push_str(&format!(
+                r#"<Default Extension="{}" ContentType="{}"/>"#,
+                escape_str(extension),
+                escape_str(content_type)
+            ));
+        }
+        for (part, content_type) in self.overrides.iter() {
+            if kept.contains(part) {
+                xml.push_str(&format!(
+                    r#"<Override PartName="/{}" ContentType="{}"/>"#,
+                    escape_str(part),
+                    escape_str(content_type)
+                ));
+            }
+        }
+        xml.push_str("</Types>");
+        xml
+    }
+}
+
+// what changes in xml merged in from another document: relationship ids, and
+// list numbers, which go after the base's
+#[derive(Default)]
+struct Renumbering {
+    ids: HashMap<String, String>,
+    num_offset: u32,
+    abstract_num_offset: u32,
+}
+
+fn add_offset(value: &[u8], offset: u32) -> Option<Vec<u8>> {
+    let number: u32 = String::from_utf8_lossy(value).parse().ok()?;
+    Some((number + offset).to_string().into_bytes())
+}
+
+fn renumber_element(e: &BytesStart, renumbering: &Renumbering) -> Option<BytesStart<'static>> {
+    let mut changed = false;
+    let mut attributes = Vec::new();
+    for attribute in e.attributes().flatten() {
+        let new_value = match (e.name(), attribute.key) {
+            // 0 turns numbering off
+            (b"w:numId", b"w:val") if &*attribute.value != b"0" => {
+                add_offset(&attribute.value, renumbering.num_offset)
+            }
+            (b"w:num", b"w:numId") => add_offset(&attribute.value, renumbering.num_offset),
+            (b"w:abstractNumId", b"w:val") | (b"w:abstractNum", b"w:abstractNumId") => {
+                add_offset(&attribute.value, renumbering.abstract_num_offset)
+            }
+            _ => renumbering
+                .ids
+                .get(&*String::from_utf8_lossy(&attribute.value))
+                .map(|id| id.clone().into_bytes()),
+        };
+        changed |= new_value.is_some();
+        attributes.push((
+            attribute.key.to_vec(),
+            new_value.unwrap_or_else(|| attribute.value.into_owned()),
+        ));
+    }
+    if !changed {
+        return None;
+    }
+    let mut element = BytesStart::owned_name(e.name().to_vec());
+    for (key, value) in attributes.iter() {
+        element.push_attribute(Attribute {
+            key,
+            value: Cow::Borrowed(value),
+        });
+    }
+    Some(element)
+}
+
+fn renumber(xml: &str, renumbering: &Renumbering) -> String {
+    let mut reader = Reader::from_str(xml);
+    let mut writer = Writer::new(Vec::with_capacity(xml.len()));
+    let mut buf = Vec::new();
+    loop {
+        let result = match reader.read_event(&mut buf) {
+            Ok(Event::Empty(ref e)) if NOTE_REFERENCES.contains(&e.name()) => Ok(()),
+            Ok(Event::Start(e)) => match renumber_element(&e, renumbering) {
+                Some(element) => writer.write_event(Event::Start(element)),
+                None => writer.write_event(Event::Start(e)),
+            },
+            Ok(Event::Empty(e)) => match renumber_element(&e, renumbering) {
+                Some(element) => writer.write_event(Event::Empty(element)),
+                None => writer.write_event(Event::Empty(e)),
+            },
+            Ok(Event::Eof) | Err(_) => break,
+            Ok(event) => writer.write_event(event),
+        };
+        // writing to a vec can't fail
+        result.ok();
+        buf.clear();
+    }
+    String::from_utf8_lossy(&writer.into_inner()).into_owned()
+}
+
+// the base's root element, with namespace declarations the other documents'
+// paragraphs need
+fn merge_namespaces(root: &str, other_roots: &[&str]) -> String {
+    let base = match read_start(&mut Reader::from_str(root)) {
+        Some(base) => base,
+        None => return root.to_string(),
+    };
+    let mut attributes: Vec<(Vec<u8>, Vec<u8>)> = base
+        .attributes()
+        .flatten()
+        .map(|attribute| (attribute.key.to_vec(), attribute.value.into_owned()))
+        .collect();
+    for other_root in other_roots {
+        let other = match read_start(&mut Reader::from_str(other_root)) {
+            Some(other) => other,
+            None => continue,
+        };
+        for attribute in other.attributes().flatten() {
+            let existing = attributes.iter().position(|(key, _)| key == attribute.key);
+            match existing {
+                // prefixes the base ignores when it doesn't know them
+                Some(i) if attribute.key == b"mc:Ignorable" => {
+                    let value = String::from_utf8_lossy(&attributes[i].1).into_owned();
+                    let mut prefixes: Vec<&str> = value.split_whitespace().collect();
+                    let other_value = String::from_utf8_lossy(&attribute.value).into_owned();
+                    for prefix in other_value.split_whitespace() {
+                        if !prefixes.contains(&prefix) {
+                            prefixes.push(prefix);
+                        }
+                    }
+                    attributes[i].1 = prefixes.join(" ").into_bytes();
+                }
+                None if attribute.key.starts_with(b"xmlns:")
+                    || attribute.key == b"mc:Ignorable" =>
+                {
+                    attributes.push((attribute.key.to_vec(), attribute.value.into_owned()));
+                }
+                _ => (),
+            }
+        }
+    }
+    let mut element = BytesStart::owned_name(base.name().to_vec());
+    for (key, value) in attributes.iter() {
+        element.push_attribute(Attribute {
+            key,
+            value: Cow::Borrowed(value),
+        });
+    }
+    let mut writer = Writer::new(Vec::new());
+    writer.write_event(Event::Start(element)).ok();
+    String::from_utf8_lossy(&writer.into_inner()).into_owned()
+}
+
+// one docx paragraphs are copied out of
+struct Source<'a> {
+    package: Package<'a>,
+    document: String,
+    body: Body,
+    copied: Vec<bool>,
+    content_types: ContentTypes,
+}
+
+// a part from another document, under its new name
+struct Part {
+    name: String,
+    contents: Vec<u8>,
+    content_type: Option<String>,
+}
+
+// the parts copied from other documents, so a part two paragraphs point to is
+// only copied once
+#[derive(Default)]
+struct Copies {
+    parts: Vec<Part>,
+    names: HashMap<(usize, String), String>,
+    taken: HashSet<String>,
+}
+
+// copies the part and everything it points to, returning its new name
+fn copy_part(
+    source: &mut Source,
+    number: usize,
+    name: &str,
+    copies: &mut Copies,
+    warnings: &mut Vec<String>,
+) -> Option<String> {
+    if let Some(new_name) = copies.names.get(&(number, name.to_string())) {
+        return Some(new_name.clone());
+    }
+    let contents = source.package.read_bytes(name, warnings)?;
+    let mut new_name = name.to_string();
+    let (dir, file) = name.rsplit_once('/').unwrap_or(("", name));
+    let (stem, extension) = match file.rsplit_once('.') {
+        Some((stem, extension)) => (stem, format!(".{}", extension)),
+        None => (file, String::new()),
+    };
+    let mut n = 1;
+    while copies.taken.contains(&new_name) {
+        new_name = format!("{}/{}-{}{}", dir, stem, n, extension);
+        n += 1;
+    }
+    copies.taken.insert(new_name.clone());
+    copies
+        .names
+        .insert((number, name.to_string()), new_name.clone());
+    let content_type = source.content_types.of(name).map(|t| t.to_string());
+    if let Some(xml) = source.package.read_part(&rels_name(name), warnings) {
+        let mut relationships = read_relationships(&xml);
+        for relationship in relationships.iter_mut() {
+            if relationship.external {
+                continue;
+            }
+            let target = resolve_target(name, &relationship.target);
+            if let Some(new_target) = copy_part(source, number, &target, copies, warnings) {
+                relationship.target = format!("/{}", new_target);
+            }
+        }
+        copies.parts.push(Part {
+            name: rels_name(&new_name),
+            contents: write_relationships(&relationships).into_bytes(),
+            content_type: Some(RELS_CONTENT_TYPE.to_string()),
+        });
+    }
+    copies.parts.push(Part {
+        name: new_name.clone(),
+        contents,
+        content_type,
+    });
+    Some(new_name)
+}
+
+/// Builds a docx out of paragraphs copied from other docx files, keeping their
+/// original xml along with the styles, numbering, images and other parts they
+/// need, so Word shows them the same way.
+///
+/// The first file is the base, whose styles, settings and page setup the new
+/// document uses. Paragraphs from other files keep their own styles when the
+/// base doesn't have a style with the same id.
+pub struct DocxWriter<'a> {
+    sources: Vec<Source<'a>>,
+    // the source and block of everything copied, in order
+    blocks: Vec<(usize, usize)>,
+}
+impl<'a> DocxWriter<'a> {
+    /// Starts a docx based on `base`, which is also source 0.
+    pub fn new(base: &'a [u8]) -> Result<DocxWriter<'a>, WriteError> {
+        let mut writer = DocxWriter {
+            sources: Vec::new(),
+            blocks: Vec::new(),
+        };
+        writer.add_source(base)?;
+        Ok(writer)
+    }
+    /// Adds another docx to copy paragraphs out of, returning the number to
+    /// pass to [`DocxWriter::copy`].
+    pub fn add_source(&mut self, bytes: &'a [u8]) -> Result<usize, WriteError> {
+        if bytes.starts_with(CFB_MAGIC) {
+            return Err(WriteError::Encrypted);
+        }
+        let mut warnings = Vec::new();
+        let mut package = Package::open(bytes, &mut warnings);
+        let document = package
+            .read_part(DOCUMENT, &mut warnings)
+            .ok_or(WriteError::NotDocx)?;
+        let body = read_body(&document)?;
+        let content_types = package
+            .read_part(CONTENT_TYPES, &mut warnings)
+            .map(|xml| ContentTypes::read(&xml))
+            .ok_or_else(|| WriteError::Damaged(format!("{} is missing", CONTENT_TYPES)))?;
+        self.sources.push(Source {
+            package,
+            copied: vec![false; body.blocks.len()],
+            document,
+            body,
+            content_types,
+        });
+        Ok(self.sources.len() - 1)
+    }
+    /// Copies `paras`, indexes in the source's [`Document::paras`], to the end
+    /// of the new document. A table is copied whole if any of its paragraphs
+    /// are, and paragraphs that were already copied are skipped.
+    ///
+    /// Panics if `source` wasn't returned by [`DocxWriter::add_source`].
+    pub fn copy(&mut self, source: usize, paras: RangeInclusive<usize>) -> Result<(), WriteError> {
+        let blocks = &self.sources[source].body.blocks;
+        let block_of = |para: usize| {
+            let i = blocks.partition_point(|block| block.paras.end <= para);
+            blocks
+                .get(i)
+                .filter(|block| block.paras.contains(&para))
+                .map(|_| i)
+                .ok_or(WriteError::MissingParagraph(para))
+        };
+        let blocks = block_of(*paras.start())?..=block_of(*paras.end())?;
+        let copied = &mut self.sources[source].copied;
+        for i in blocks {
+            if !copied[i] {
+                copied[i] = true;
+                self.blocks.push((source, i));
+            }
+        }
+        Ok(())
+    }
+    /// The new docx.
+    pub fn finish(mut self) -> Result<Vec<u8>, WriteError> {
+        let mut warnings = Vec::new();
+        let (base, others) = self.sources.split_first_mut().unwrap();
+        let base_part_names = base.package.part_names();
+        let base_rels = base
+            .package
+            .read_part(DOCUMENT_RELS, &mut warnings)
+            .map(|xml| read_relationships(&xml))
+            .unwrap_or_default();
+        let mut styles = base.package.read_part(STYLES, &mut warnings);
+        let mut numbering = base.package.read_part(NUMBERING, &mut warnings);
+
+        // merge in what the other documents' paragraphs need
+        let mut copies = Copies {
+            taken: base_part_names.iter().cloned().collect(),
+            ..Copies::default()
+        };
+        let mut merged_rels = Vec::new();
+        let mut style_ids: HashSet<String> = HashSet::new();
+        let mut added_styles = String::new();
+        // list ids start at 1 and abstract list ids at 0
+        let (mut max_num, mut next_abstract_num) = (0, 0);
+        if let Some(numbering) = &numbering {
+            for (name, range) in read_children(numbering).0 {
+                let xml = &numbering[range];
+                match name.as_slice() {
+                    b"w:num" => max_num = max_num.max(number_attr(xml, b"w:numId")),
+                    b"w:abstractNum" => {
+                        next_abstract_num =
+                            next_abstract_num.max(number_attr(xml, b"w:abstractNumId") + 1)
+                    }
+                    _ => (),
+                }
+            }
+        }
+        if let Some(styles) = &styles {
+            for (_, range) in read_children(styles).0 {
+                if let Some(id) = first_attr(&styles[range], b"w:styleId") {
+                    style_ids.insert(id);
+                }
+            }
+        }
+        let (mut added_abstract_nums, mut added_nums) = (String::new(), String::new());
+        let mut renumberings: Vec<Renumbering> = Vec::new();
+        for (i, source) in others.iter_mut().enumerate() {
+            let number = i + 1;
+            let mut renumbering = Renumbering {
+                num_offset: max_num,
+                abstract_num_offset: next_abstract_num,
+                ..Renumbering::default()
+            };
+            if !source.copied.contains(&true) {
+                renumberings.push(renumbering);
+                continue;
+            }
+            let copied_xml: String = source
+                .body
+                .blocks
+                .iter()
+                .zip(source.copied.iter())
+                .filter(|(_, &copied)| copied)
+                .map(|(block, _)| &source.document[block.range.clone()])
+                .collect();
+            let used = attribute_values(&copied_xml);
+            let used_before = attribute_values(&source.document);
+            let relationships = source
+                .package
+                .read_part(DOCUMENT_RELS, &mut warnings)
+                .map(|xml| read_relationships(&xml))
+                .unwrap_or_default();
+            for relationship in relationships {
+                if !(used.contains(&relationship.id) && used_before.contains(&relationship.id)) {
+                    continue;
+                }
+                let target = if relationship.external {
+                    relationship.target.clone()
+                } else {
+                    let target = resolve_target(DOCUMENT, &relationship.target);
+                    match copy_part(source, number, &target, &mut copies, &mut warnings) {
+                        Some(new_target) => format!("/{}", new_target),
+                        None => continue,
+                    }
+                };
+                let id = format!("s{}{}", number, relationship.id);
+                renumbering.ids.insert(relationship.id.clone(), id.clone());
+                merged_rels.push(Relationship {
+                    id,
+                    target,
+                    ..relationship
+                });
+            }
+            // lists go after the base's and any other document's before this one
+            if let Some(other_numbering) = source.package.read_part(NUMBERING, &mut warnings) {
+                for (name, range) in read_children(&other_numbering).0 {
+                    let xml = &other_numbering[range];
+                    match name.as_slice() {
+                        b"w:abstractNum" => {
+                            let id = number_attr(xml, b"w:abstractNumId");
+                            next_abstract_num =
+                                next_abstract_num.max(id + renumbering.abstract_num_offset + 1);
+                            added_abstract_nums.push_str(&renumber(xml, &renumbering));
+                        }
+                        b"w:num" => {
+                            let id = number_attr(xml, b"w:numId");
+                            max_num = max_num.max(id + renumbering.num_offset);
+                            added_nums.push_str(&renumber(xml, &renumbering));
+                        }
+                        _ => (),
+                    }
+                }
+            }
+            if let Some(other_styles) = source.package.read_part(STYLES, &mut warnings) {
+                for (name, range) in read_children(&other_styles).0 {
+                    let xml = &other_styles[range];
+                    if name != b"w:style" {
+                        continue;
+                    }
+                    if let Some(id) = first_attr(xml, b"w:styleId") {
+                        if style_ids.insert(id) {
+                            added_styles.push_str(&renumber(xml, &renumbering));
+                        }
+                    }
+                }
+            }
+            renumberings.push(renumbering);
+        }
+        if let Some(styles) = &mut styles {
+            let end = read_children(styles).1;
+            styles.insert_str(end, &added_styles);
+        }
+        let mut document_rels: Vec<Relationship> = Vec::new();
+        if !added_nums.is_empty() {
+            match &mut numbering {
+                Some(numbering) => {
+                    // abstract numbering all comes before numbering
+                    let (children, end) = read_children(numbering);
+                    let nums_end = children
+                        .iter()
+                        .find(|(name, _)| name == b"w:numIdMacAtCleanup")
+                        .map_or(end, |(_, range)| range.start);
+                    let abstract_nums_end = children
+                        .iter()
+                        .find(|(name, _)| name == b"w:num")
+                        .map_or(nums_end, |(_, range)| range.start);
+                    numbering.insert_str(nums_end, &added_nums);
+                    numbering.insert_str(abstract_nums_end, &added_abstract_nums);
+                }
+                None => {
+                    numbering = Some(format!(
+                        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+                         <w:numbering xmlns:w=\"http://schemas.openxmlformats.org/wordprocessingml/2006/main\">{}{}</w:numbering>",
+                        added_abstract_nums, added_nums
+                    ));
+                    document_rels.push(Relationship {
+                        id: "sNumbering".to_string(),
+                        kind: NUMBERING_TYPE.to_string(),
+                        target: "numbering.xml".to_string(),
+                        external: false,
+                    });
+                    base.content_types
+                        .overrides
+                        .push((NUMBERING.to_string(), NUMBERING_CONTENT_TYPE.to_string()));
+                }
+            }
+        }
+
+        let document = &base.document;
+        let mut new_document = String::with_capacity(document.len());
+        let other_roots: Vec<&str> = others
+            .iter()
+            .filter(|source| source.copied.contains(&true))
+            .map(|source| &source.document[source.body.root.clone()])
+            .collect();
+        new_document.push_str(&document[..base.body.root.start]);
+        new_document.push_str(&merge_namespaces(
+            &document[base.body.root.clone()],
+            &other_roots,
+        ));
+        new_document.push_str(&document[base.body.root.end..base.body.contents.start]);
+        for &(source, block) in self.blocks.iter() {
+            if source == 0 {
+                new_document.push_str(&document[base.body.blocks[block].range.clone()]);
+            } else {
+                let other = &others[source - 1];
+                new_document.push_str(&renumber(
+                    &other.document[other.body.blocks[block].range.clone()],
+                    &renumberings[source - 1],
+                ));
+            }
+        }
+        if let Some(sect_pr) = base.body.sect_pr.clone() {
+            new_document.push_str(&document[sect_pr]);
+        }
+        new_document.push_str(&document[base.body.contents.end..]);
+
+        // relationships the base's paragraphs point to are dropped if none of
+        // the copied ones do. the rest belong to the whole document
+        let used_before = attribute_values(document);
+        let used = attribute_values(&new_document);
+        document_rels.extend(
+            base_rels
+                .into_iter()
+                .filter(|relationship| {
+                    used.contains(&relationship.id) || !used_before.contains(&relationship.id)
+                })
+                .chain(merged_rels),
+        );
+
+        // keep every part of the base that can still be reached from the
+        // package's relationships
+        let parts: HashSet<&String> = base_part_names.iter().collect();
+        let mut kept: HashSet<String> = HashSet::new();
+        kept.insert(DOCUMENT.to_string());
+        let mut unvisited = vec![String::new(), DOCUMENT.to_string()];
+        while let Some(part) = unvisited.pop() {
+            let rels_name = rels_name(&part);
+            let relationships = if part == DOCUMENT {
+                document_rels.clone()
+            } else {
+                match base.package.read_part(&rels_name, &mut warnings) {
+                    Some(xml) => read_relationships(&xml),
+                    None if part.is_empty() => {
+                        return Err(WriteError::Damaged(format!("{} is missing", PACKAGE_RELS)));
+                    }
+                    None => continue,
+                }
+            };
+            kept.insert(rels_name);
+            for relationship in relationships {
+                let target = resolve_target(&part, &relationship.target);
+                if !relationship.external && parts.contains(&target) && kept.insert(target.clone())
+                {
+                    unvisited.push(target);
+                }
+            }
+        }
+        if numbering.is_some() {
+            kept.insert(NUMBERING.to_string());
+        }
+        for part in copies.parts.iter() {
+            if let Some(content_type) = &part.content_type {
+                base.content_types
+                    .overrides
+                    .push((part.name.clone(), content_type.clone()));
+            }
+            kept.insert(part.name.clone());
+        }
+
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+        // word expects the content types first
+        zip.start_file(CONTENT_TYPES, options)?;
+        zip.write_all(base.content_types.write(&kept).as_bytes())?;
+        let mut part_names = base_part_names.clone();
+        if numbering.is_some() && !parts.contains(&NUMBERING.to_string()) {
+            part_names.push(NUMBERING.to_string());
+        }
+        if !parts.contains(&DOCUMENT_RELS.to_string()) && !document_rels.is_empty() {
+            part_names.push(DOCUMENT_RELS.to_string());
+        }
+        for name in part_names.iter() {
+            if name == CONTENT_TYPES || !kept.contains(name) {
+                continue;
+            }
+            let contents = match name.as_str() {
+                DOCUMENT => new_document.as_bytes().to_vec(),
+                DOCUMENT_RELS => write_relationships(&document_rels).into_bytes(),
+                STYLES if styles.is_some() => styles.take().unwrap_or_default().into_bytes(),
+                NUMBERING if numbering.is_some() => {
+                    numbering.take().unwrap_or_default().into_bytes()
+                }
+                _ => match base.package.read_bytes(name, &mut warnings) {
+                    Some(contents) => contents,
+                    None => continue,
+                },
+            };
+            zip.start_file(name.as_str(), options)?;
+            zip.write_all(&contents)?;
+        }
+        for part in copies.parts.iter() {
+            zip.start_file(part.name.as_str(), options)?;
+            zip.write_all(&part.contents)?;
+        }
+        Ok(zip.finish()?.into_inner())
+    }
+}
+
+// a number in an attribute of the element that starts `xml`, or 0
+fn number_attr(xml: &str, name: &[u8]) -> u32 {
+    first_attr(xml, name)
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(0)
+}
+
+impl Export<'_> {
+    /// A new docx with the section under each of `links`, the indexes of
+    /// headings in [`Document::paras`], copied out of `source`, the docx
+    /// this was loaded from. Sections are copied in the order of `links`, and
+    /// paragraphs that are already in an earlier section aren't copied again.
+    ///
+    /// Paragraphs keep their original xml, along with the styles, numbering,
+    /// images and other parts they need, so Word shows them exactly like the
+    /// source. A table is copied whole if any of its paragraphs are.
+    pub fn sections_to_docx(&self, source: &[u8], links: &[usize]) -> Result<Vec<u8>, WriteError> {
+        let mut writer = DocxWriter::new(source)?;
+        for &link in links {
+            let section = self
+                .section_at(link)
+                .ok_or(WriteError::MissingParagraph(link))?;
+            writer.copy(0, section.para_range())?;
+        }
+        writer.finish()
+    }
+}
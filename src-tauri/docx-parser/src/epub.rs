@@ -0,0 +1,394 @@
+use crate::document::{OutlinePara, Para, Run};
+use crate::export::Export;
+use crate::html::escape_html;
+use crate::metadata::DocumentInfo;
+
+use std::fmt::Write as _;
+use std::io::{Cursor, Write};
+use std::ops::Range;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sha2::{Digest, Sha256};
+use zip::write::{FileOptions, ZipWriter};
+use zip::CompressionMethod;
+
+// e-readers have their own fonts and margins, so only the formatting is set
+const CSS: &str = "\
+h1, h2, h3, h4, h5, h6 { margin: 1em 0 0.3em; }
+p { margin: 0 0 0.5em; }
+.underline { text-decoration: underline; }
+.highlight { background-color: yellow; }
+";
+
+const CONTAINER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+<rootfiles>
+<rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+</rootfiles>
+</container>
+"#;
+
+const XHTML_START: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE html>\n\
+<html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\">\n";
+
+fn push_run(xhtml: &mut String, run: &Run) {
+    let style = &run.style;
+    let mut classes = Vec::new();
+    if style.underline == Some(true) {
+        classes.push("underline");
+    }
+    if style.highlight == Some(true) {
+        classes.push("highlight");
+    }
+    let mut close = Vec::new();
+    if !classes.is_empty() || style.size.is_some() {
+        xhtml.push_str("<span");
+        if !classes.is_empty() {
+            let _ = write!(xhtml, r#" class="{}""#, classes.join(" "));
+        }
+        if let Some(size) = style.size {
+            // relative to the reader's font size, which is taken to be 12pt
+            let _ = write!(
+                xhtml,
+                r#" style="font-size: {}em""#,
+                (size as f32 / 24.0 * 100.0).round() / 100.0
+            );
+        }
+        xhtml.push('>');
+        close.push("</span>");
+    }
+    if style.bold == Some(true) {
+        xhtml.push_str("<b>");
+        close.push("</b>");
+    }
+    xhtml.push_str(&escape_html(&run.text));
+    for close_tag in close.iter().rev() {
+        xhtml.push_str(close_tag);
+    }
+}
+
+fn push_para(xhtml: &mut String, para: &Para) {
+    let tag = match para.outline_level {
+        Some(level) => format!("h{}", level.min(5) + 1),
+        None => "p".to_string(),
+    };
+    match para.outline_level {
+        // headings are linked to from the table of contents
+        Some(_) => {
+            let _ = write!(xhtml, r#"<{} id="p{}">"#, tag, para.index);
+        }
+        None => {
+            let _ = write!(xhtml, "<{}>", tag);
+        }
+    }
+    if para.runs.is_empty() {
+        xhtml.push_str("<br/>");
+    }
+    for run in para.runs.iter() {
+        push_run(xhtml, run);
+    }
+    let _ = writeln!(xhtml, "</{}>", tag);
+}
+
+fn heading_text(para: &OutlinePara) -> String {
+    let text: String = para.runs.iter().map(|run| run.text.as_str()).collect();
+    text.trim().to_string()
+}
+
+// a heading and the ones under it, as positions in the outline
+struct Entry {
+    position: usize,
+    children: Vec<Entry>,
+}
+
+// nests each heading under the last one with a lower level. Skipped levels
+// aren't filled in, a level 3 under a level 1 is one deeper
+fn outline_tree(outline_paras: &[OutlinePara]) -> Vec<Entry> {
+    fn build(outline_paras: &[OutlinePara], next: &mut usize, parent: Option<u32>) -> Vec<Entry> {
+        let mut entries = Vec::new();
+        while let Some(para) = outline_paras.get(*next) {
+            let level = para.outline_level.unwrap_or(0);
+            if parent.map_or(false, |parent| level <= parent) {
+                break;
+            }
+            *next += 1;
+            entries.push(Entry {
+                position: *next - 1,
+                children: build(outline_paras, next, Some(level)),
+            });
+        }
+        entries
+    }
+    build(outline_paras, &mut 0, None)
+}
+
+// W3CDTF without fractions, which is what dcterms:modified has to be
+fn is_timestamp(value: &str) -> bool {
+    let bytes = value.as_bytes();
+    bytes.len() == 20
+        && bytes.iter().enumerate().all(|(i, &b)| match i {
+            4 | 7 => b == b'-',
+            10 => b == b'T',
+            13 | 16 => b == b':',
+            19 => b == b'Z',
+            _ => b.is_ascii_digit(),
+        })
+}
+
+fn timestamp(seconds: u64) -> String {
+    let days = (seconds / 86400) as i64;
+    let time = seconds % 86400;
+    // days to a civil date, from Howard Hinnant's date algorithms
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+fn modified(info: &DocumentInfo) -> String {
+    [&info.modified, &info.created]
+        .iter()
+        .filter_map(|value| value.as_deref())
+        .find(|value| is_timestamp(value))
+        .map(str::to_string)
+        .unwrap_or_else(|| {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_secs());
+            timestamp(now)
+        })
+}
+
+fn chapter_name(chapter: usize) -> String {
+    format!("chapter{}.xhtml", chapter + 1)
+}
+
+impl Export<'_> {
+    /// An EPUB 3 book, with a chapter for each heading at `split_level` or
+    /// higher (0 is the top level) and whatever comes before the first one.
+    /// The table of contents has every heading, in both the EPUB 3 nav
+    /// document and the NCX older readers use.
+    pub fn to_epub(&self, info: &DocumentInfo, split_level: u32) -> Vec<u8> {
+        let chapters = self.chapters(split_level);
+        let chapter_of = |index: usize| {
+            chapters.partition_point(|chapter| self.paras[chapter.start].index <= index) - 1
+        };
+        let mut files: Vec<(String, String)> = Vec::new();
+        for (i, chapter) in chapters.iter().enumerate() {
+            let paras = &self.paras[chapter.clone()];
+            let heading = paras
+                .first()
+                .filter(|para| para.outline_level.is_some())
+                .map(|para| para.text().trim().to_string())
+                .unwrap_or_default();
+            let title = if heading.is_empty() {
+                self.title.clone()
+            } else {
+                heading
+            };
+            let mut xhtml = String::from(XHTML_START);
+            let _ = writeln!(
+                xhtml,
+                "<head>\n<title>{}</title>\n<link rel=\"stylesheet\" type=\"text/css\" href=\"style.css\"/>\n</head>\n<body>",
+                escape_html(&title)
+            );
+            for para in paras.iter() {
+                push_para(&mut xhtml, para);
+            }
+            xhtml.push_str("</body>\n</html>\n");
+            files.push((chapter_name(i), xhtml));
+        }
+        let tree = outline_tree(self.outline_paras);
+        let href = |position: usize| {
+            let para = &self.outline_paras[position];
+            format!("{}#p{}", chapter_name(chapter_of(para.link)), para.link)
+        };
+
+        let mut nav = String::from(XHTML_START);
+        let _ = writeln!(
+            nav,
+            "<head>\n<title>{}</title>\n</head>\n<body>\n<nav epub:type=\"toc\" id=\"toc\">\n<h1>Contents</h1>",
+            escape_html(&self.title)
+        );
+        fn push_nav(nav: &mut String, entries: &[Entry], label: &dyn Fn(&Entry) -> String) {
+            nav.push_str("<ol>\n");
+            for entry in entries {
+                nav.push_str(&label(entry));
+                if !entry.children.is_empty() {
+                    nav.push('\n');
+                    push_nav(nav, &entry.children, label);
+                }
+                nav.push_str("</li>\n");
+            }
+            nav.push_str("</ol>\n");
+        }
+        let nav_label = |entry: &Entry| {
+            format!(
+                r#"<li><a href="{}">{}</a>"#,
+                href(entry.position),
+                escape_html(&heading_text(&self.outline_paras[entry.position]))
+            )
+        };
+        if tree.is_empty() {
+            // the nav needs at least one link
+            let _ = writeln!(
+                nav,
+                "<ol>\n<li><a href=\"{}\">{}</a></li>\n</ol>",
+                chapter_name(0),
+                escape_html(&self.title)
+            );
+        } else {
+            push_nav(&mut nav, &tree, &nav_label);
+        }
+        nav.push_str("</nav>\n</body>\n</html>\n");
+
+        // the identifier only has to be unique, so it's made from the contents
+        let mut hasher = Sha256::new();
+        for (_, xhtml) in files.iter() {
+            hasher.update(xhtml.as_bytes());
+        }
+        let identifier = format!("urn:docx-reader:{:x}", hasher.finalize());
+
+        let mut ncx = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        ncx.push_str("<ncx xmlns=\"http://www.daisy.org/z3986/2005/ncx/\" version=\"2005-1\">\n");
+        let _ = writeln!(
+            ncx,
+            "<head>\n<meta name=\"dtb:uid\" content=\"{}\"/>\n</head>\n<docTitle><text>{}</text></docTitle>\n<navMap>",
+            identifier,
+            escape_html(&self.title)
+        );
+        fn push_ncx(ncx: &mut String, entries: &[Entry], point: &dyn Fn(&Entry) -> String) {
+            for entry in entries {
+                ncx.push_str(&point(entry));
+                push_ncx(ncx, &entry.children, point);
+                ncx.push_str("</navPoint>\n");
+            }
+        }
+        let ncx_point = |entry: &Entry| {
+            // positions in the outline are in reading order, like playOrder
+            format!(
+                "<navPoint id=\"nav{}\" playOrder=\"{}\"><navLabel><text>{}</text></navLabel><content src=\"{}\"/>\n",
+                entry.position,
+                entry.position + 1,
+                escape_html(&heading_text(&self.outline_paras[entry.position])),
+                href(entry.position)
+            )
+        };
+        if tree.is_empty() {
+            let _ = writeln!(
+                ncx,
+                "<navPoint id=\"nav0\" playOrder=\"1\"><navLabel><text>{}</text></navLabel><content src=\"{}\"/></navPoint>",
+                escape_html(&self.title),
+                chapter_name(0)
+            );
+        } else {
+            push_ncx(&mut ncx, &tree, &ncx_point);
+        }
+        ncx.push_str("</navMap>\n</ncx>\n");
+
+        let mut opf = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        opf.push_str("<package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" unique-identifier=\"id\">\n");
+        opf.push_str("<metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n");
+        let _ = writeln!(
+            opf,
+            "<dc:identifier id=\"id\">{}</dc:identifier>",
+            identifier
+        );
+        let _ = writeln!(opf, "<dc:title>{}</dc:title>", escape_html(&self.title));
+        // documents don't say what language they're in
+        opf.push_str("<dc:language>en</dc:language>\n");
+        if let Some(creator) = &info.creator {
+            let _ = writeln!(opf, "<dc:creator>{}</dc:creator>", escape_html(creator));
+        }
+        let _ = writeln!(
+            opf,
+            "<meta property=\"dcterms:modified\">{}</meta>\n</metadata>\n<manifest>",
+            modified(info)
+        );
+        opf.push_str("<item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>\n");
+        opf.push_str(
+            "<item id=\"ncx\" href=\"toc.ncx\" media-type=\"application/x-dtbncx+xml\"/>\n",
+        );
+        opf.push_str("<item id=\"css\" href=\"style.css\" media-type=\"text/css\"/>\n");
+        for i in 0..files.len() {
+            let _ = writeln!(
+                opf,
+                "<item id=\"chapter{}\" href=\"{}\" media-type=\"application/xhtml+xml\"/>",
+                i + 1,
+                chapter_name(i)
+            );
+        }
+        opf.push_str("</manifest>\n<spine toc=\"ncx\">\n");
+        for i in 0..files.len() {
+            let _ = writeln!(opf, "<itemref idref=\"chapter{}\"/>", i + 1);
+        }
+        opf.push_str("</spine>\n</package>\n");
+
+        files.push(("content.opf".to_string(), opf));
+        files.push(("nav.xhtml".to_string(), nav));
+        files.push(("toc.ncx".to_string(), ncx));
+        files.push(("style.css".to_string(), CSS.to_string()));
+        // writing to memory only fails on a bug in zip
+        write_epub(&files).unwrap()
+    }
+    /// Ranges of positions in `paras`, starting at each heading at
+    /// `split_level` or higher. Never empty, a document without paragraphs
+    /// still has one empty chapter.
+    fn chapters(&self, split_level: u32) -> Vec<Range<usize>> {
+        let mut starts: Vec<usize> = self
+            .paras
+            .iter()
+            .enumerate()
+            .filter(|(_, para)| {
+                para.outline_level
+                    .map_or(false, |level| level <= split_level)
+            })
+            .map(|(i, _)| i)
+            .collect();
+        if starts.first() != Some(&0) {
+            starts.insert(0, 0);
+        }
+        let mut chapters: Vec<Range<usize>> =
+            starts.windows(2).map(|pair| pair[0]..pair[1]).collect();
+        chapters.push(starts[starts.len() - 1]..self.paras.len());
+        chapters
+    }
+}
+
+// the mimetype has to come first and uncompressed, so readers can recognize
+// the file without unzipping it
+fn write_epub(files: &[(String, String)]) -> zip::result::ZipResult<Vec<u8>> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.start_file(
+        "mimetype",
+        FileOptions::default().compression_method(CompressionMethod::Stored),
+    )?;
+    zip.write_all(b"application/epub+zip")?;
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    zip.start_file("META-INF/container.xml", options)?;
+    zip.write_all(CONTAINER.as_bytes())?;
+    for (name, contents) in files {
+        zip.start_file(format!("OEBPS/{}", name), options)?;
+        zip.write_all(contents.as_bytes())?;
+    }
+    Ok(zip.finish()?.into_inner())
+}
@@ -0,0 +1,74 @@
+use crate::document::{Document, OutlinePara, Para};
+
+use std::ops::RangeInclusive;
+
+/// A document, or one section of it, to write out in another format.
+#[derive(Clone)]
+pub struct Export<'a> {
+    pub title: String,
+    pub paras: &'a [Para],
+    /// The headings in `paras`.
+    pub outline_paras: &'a [OutlinePara],
+}
+impl<'a> Export<'a> {
+    pub fn new(title: &str, paras: &'a [Para], outline_paras: &'a [OutlinePara]) -> Export<'a> {
+        Export {
+            title: title.to_string(),
+            paras,
+            outline_paras,
+        }
+    }
+    /// Narrows the export to `paras[index]` and, if it's a heading, everything
+    /// under it up to the next heading at the same or a higher level. This is
+    /// the same section the app copies with a heading's children. The
+    /// heading's text becomes the title.
+    pub fn section(&self, index: usize) -> Option<Export<'a>> {
+        let heading = self.paras.get(index)?;
+        let len = match heading.outline_level {
+            Some(level) => self.paras[index + 1..]
+                .iter()
+                .position(|para| para.outline_level.map_or(false, |other| other <= level))
+                .map_or(self.paras.len() - index, |len| len + 1),
+            None => 1,
+        };
+        let paras = &self.paras[index..index + len];
+        // outline paras are in document order, so the section's are together
+        let first = paras[0].index;
+        let last = paras[len - 1].index;
+        let start = self.outline_paras.partition_point(|para| para.link < first);
+        let end = self.outline_paras.partition_point(|para| para.link <= last);
+        let title = heading.text().trim().to_string();
+        Some(Export {
+            title: if title.is_empty() {
+                self.title.clone()
+            } else {
+                title
+            },
+            paras,
+            outline_paras: &self.outline_paras[start..end],
+        })
+    }
+    /// Like [`Export::section`], with `link` an index in [`Document::paras`]
+    /// instead of a position in `paras`.
+    pub fn section_at(&self, link: usize) -> Option<Export<'a>> {
+        let position = self
+            .paras
+            .binary_search_by_key(&link, |para| para.index)
+            .ok()?;
+        self.section(position)
+    }
+    /// The indexes in [`Document::paras`] of the first and last paragraph.
+    ///
+    /// Panics if there are no paragraphs, which a section always has.
+    pub fn para_range(&self) -> RangeInclusive<usize> {
+        self.paras[0].index..=self.paras[self.paras.len() - 1].index
+    }
+}
+
+impl Document {
+    /// Everything in the document, to export. `title` is used when the
+    /// document doesn't have one in its properties.
+    pub fn export(&self, title: &str) -> Export<'_> {
+        Export::new(self.info.title_or(title), &self.paras, &self.outline_paras)
+    }
+}
@@ -0,0 +1,140 @@
+use crate::document::Document;
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+// single file word xml: either a flat opc package (pkg:package) holding every
+// part inline, or the word 2003 format (w:wordDocument) with styles and body
+// directly under the root. both get cut into the same xml load_style_map and
+// load_paras read from a zipped docx.
+impl Document {
+    /// Loads a Flat OPC or Word 2003 XML document.
+    pub fn load_flat_xml(&mut self, contents: &str) {
+        match Self::root_name(contents).as_deref() {
+            Some("pkg:package") => self.load_flat_opc(contents),
+            Some("w:wordDocument") => self.load_word_2003(contents),
+            Some(root) => self.warnings.push(format!(
+                "xml with a {} root isn't a Flat OPC or Word 2003 document, the text was lost",
+                root
+            )),
+            None => self
+                .warnings
+                .push("the xml has no root element, the text was lost".to_string()),
+        }
+    }
+    fn load_flat_opc(&mut self, contents: &str) {
+        let parts = Self::find_flat_parts(contents);
+        // like load_doc_props, every part is optional
+        for name in [
+            "/docProps/core.xml",
+            "/docProps/app.xml",
+            "/docProps/custom.xml",
+        ] {
+            if let Some(part) = parts.get(name) {
+                self.read_props(&contents[part.clone()]);
+            }
+        }
+        if let Some(styles) = parts.get("/word/styles.xml") {
+            self.load_style_map(&contents[styles.clone()]);
+        }
+        match parts.get("/word/document.xml") {
+            Some(document) => self.load_paras(&contents[document.clone()]),
+            None => self
+                .warnings
+                .push("/word/document.xml is missing, the text was lost".to_string()),
+        }
+    }
+    fn load_word_2003(&mut self, contents: &str) {
+        if let Some(styles) = Self::find_element(contents, b"w:styles") {
+            self.load_style_map(&contents[styles]);
+        }
+        if let Some(document) = Self::find_element(contents, b"w:body") {
+            self.load_paras(&contents[document]);
+        }
+    }
+    // name of the first element, past the xml declaration and any comments
+    fn root_name(contents: &str) -> Option<String> {
+        let mut reader = Reader::from_str(contents);
+        let mut buf = Vec::new();
+        loop {
+            match reader.read_event(&mut buf) {
+                Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) => {
+                    return Some(String::from_utf8_lossy(e.name()).into_owned())
+                }
+                Ok(Event::Eof) | Err(_) => return None,
+                _ => (),
+            }
+            buf.clear();
+        }
+    }
+    // byte ranges of the xml inside each pkg:part's pkg:xmlData, by part name
+    fn find_flat_parts(contents: &str) -> HashMap<String, Range<usize>> {
+        let mut reader = Reader::from_str(contents);
+        let mut buf = Vec::new();
+        let mut parts = HashMap::new();
+        let mut part_name: Option<String> = None;
+        let mut data_start: Option<usize> = None;
+        loop {
+            let position = reader.buffer_position();
+            match reader.read_event(&mut buf) {
+                Ok(Event::Start(ref e)) => match e.name() {
+                    b"pkg:part" => part_name = Self::get_attr(&reader, e, b"pkg:name"),
+                    b"pkg:xmlData" => data_start = Some(reader.buffer_position()),
+                    _ => (),
+                },
+                Ok(Event::End(ref e)) => match e.name() {
+                    b"pkg:xmlData" => {
+                        if let (Some(name), Some(start)) = (part_name.clone(), data_start.take()) {
+                            parts.insert(name, start..position);
+                        }
+                    }
+                    b"pkg:part" => part_name = None,
+                    _ => (),
+                },
+                Ok(Event::Eof) => break,
+                // keep the parts found so far, and the part that was cut short,
+                // which gets reported when it's read
+                Err(_) => {
+                    if let (Some(name), Some(start)) = (part_name, data_start) {
+                        parts.insert(name, start..position);
+                    }
+                    break;
+                }
+                _ => (),
+            }
+            buf.clear();
+        }
+        parts
+    }
+    // byte range of the first element with this name, tags included
+    fn find_element(contents: &str, name: &[u8]) -> Option<Range<usize>> {
+        let mut reader = Reader::from_str(contents);
+        let mut buf = Vec::new();
+        let mut start: Option<usize> = None;
+        let mut depth = 0;
+        loop {
+            let position = reader.buffer_position();
+            match reader.read_event(&mut buf) {
+                Ok(Event::Start(ref e)) if e.name() == name => {
+                    if start.is_none() {
+                        start = Some(position);
+                    }
+                    depth += 1;
+                }
+                Ok(Event::End(ref e)) if e.name() == name => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return start.map(|start| start..reader.buffer_position());
+                    }
+                }
+                Ok(Event::Eof) => return None,
+                Err(_) => return start.map(|start| start..position),
+                _ => (),
+            }
+            buf.clear();
+        }
+    }
+}
@@ -0,0 +1,84 @@
+// metrics for the standard 14 Helvetica fonts, which every pdf reader has, so
+// nothing needs to be embedded. widths are in thousandths of the font size, for
+// WinAnsiEncoding codes 32 to 255, from Adobe's AFM files.
+
+pub(crate) const HELVETICA: [u16; 224] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667,
+    611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500,
+    222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+    350, 556, 350, 222, 556, 333, 1000, 556, 556, 333, 1000, 667, 333, 1000, 350, 611, 350, 350,
+    222, 222, 333, 333, 350, 556, 1000, 333, 1000, 500, 333, 944, 350, 500, 667, 278, 333, 556,
+    556, 556, 556, 260, 556, 333, 737, 370, 556, 584, 333, 737, 333, 400, 584, 333, 333, 333, 556,
+    537, 278, 333, 333, 365, 556, 834, 834, 834, 611, 667, 667, 667, 667, 667, 667, 1000, 722, 667,
+    667, 667, 667, 278, 278, 278, 278, 722, 722, 778, 778, 778, 778, 778, 584, 778, 722, 722, 722,
+    722, 667, 667, 611, 556, 556, 556, 556, 556, 556, 889, 500, 556, 556, 556, 556, 222, 222, 222,
+    222, 556, 556, 556, 556, 556, 556, 556, 584, 611, 556, 556, 556, 556, 500, 556, 500,
+];
+
+pub(crate) const HELVETICA_BOLD: [u16; 224] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611, 975, 722, 722, 722, 722, 667,
+    611, 778, 722, 278, 556, 722, 611, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 333, 278, 333, 584, 556, 333, 556, 611, 556, 611, 556, 333, 611, 611, 278, 278, 556,
+    278, 889, 611, 611, 611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389, 280, 389, 584,
+    350, 556, 350, 278, 556, 500, 1000, 556, 556, 333, 1000, 667, 333, 1000, 350, 611, 350, 350,
+    278, 278, 500, 500, 350, 556, 1000, 333, 1000, 556, 333, 944, 350, 500, 667, 278, 333, 556,
+    556, 556, 556, 280, 556, 333, 737, 370, 556, 584, 333, 737, 333, 400, 584, 333, 333, 333, 611,
+    556, 278, 333, 333, 365, 556, 834, 834, 834, 611, 722, 722, 722, 722, 722, 722, 1000, 722, 667,
+    667, 667, 667, 278, 278, 278, 278, 722, 722, 778, 778, 778, 778, 778, 584, 778, 722, 722, 722,
+    722, 667, 667, 611, 556, 556, 556, 556, 556, 556, 889, 556, 556, 556, 556, 556, 278, 278, 278,
+    278, 611, 611, 611, 611, 611, 611, 611, 584, 611, 611, 611, 611, 611, 556, 611, 556,
+];
+
+// the characters WinAnsiEncoding puts in 128 to 159, sorted by character
+const WIN_ANSI_EXTRA: [(char, u8); 27] = [
+    ('\u{0152}', 140),
+    ('\u{0153}', 156),
+    ('\u{0160}', 138),
+    ('\u{0161}', 154),
+    ('\u{0178}', 159),
+    ('\u{017d}', 142),
+    ('\u{017e}', 158),
+    ('\u{0192}', 131),
+    ('\u{02c6}', 136),
+    ('\u{02dc}', 152),
+    ('\u{2013}', 150),
+    ('\u{2014}', 151),
+    ('\u{2018}', 145),
+    ('\u{2019}', 146),
+    ('\u{201a}', 130),
+    ('\u{201c}', 147),
+    ('\u{201d}', 148),
+    ('\u{201e}', 132),
+    ('\u{2020}', 134),
+    ('\u{2021}', 135),
+    ('\u{2022}', 149),
+    ('\u{2026}', 133),
+    ('\u{2030}', 137),
+    ('\u{2039}', 139),
+    ('\u{203a}', 155),
+    ('\u{20ac}', 128),
+    ('\u{2122}', 153),
+];
+
+/// The WinAnsiEncoding code for a character, or `?` for characters the
+/// standard fonts don't have.
+pub(crate) fn win_ansi(c: char) -> u8 {
+    match c as u32 {
+        32..=126 | 160..=255 => c as u8,
+        _ => WIN_ANSI_EXTRA
+            .binary_search_by_key(&c, |&(extra, _)| extra)
+            .map_or(b'?', |i| WIN_ANSI_EXTRA[i].1),
+    }
+}
+
+/// The width of a WinAnsiEncoding code at size 1.
+pub(crate) fn width(code: u8, bold: bool) -> f32 {
+    let widths = if bold { &HELVETICA_BOLD } else { &HELVETICA };
+    match code {
+        32..=255 => widths[code as usize - 32] as f32 / 1000.0,
+        _ => 0.0,
+    }
+}
@@ -0,0 +1,147 @@
+use crate::document::{Para, Run};
+use crate::export::Export;
+
+use std::fmt::Write;
+
+// the outline goes in a sidebar on wide screens and above the text on narrow
+// ones, and is left out when printing
+const CSS: &str = "\
+body { margin: 0; font-family: Calibri, Carlito, sans-serif; line-height: 1.4; color: #222; background: #fff; }
+nav { position: fixed; top: 0; bottom: 0; left: 0; width: 18rem; box-sizing: border-box; overflow-y: auto; padding: 1rem; border-right: 1px solid #ddd; font-size: 0.9rem; }
+nav ul { list-style: none; margin: 0; padding-left: 1rem; }
+nav > ul { padding-left: 0; }
+nav a { display: block; padding: 0.1rem 0; color: inherit; text-decoration: none; }
+nav a:hover { text-decoration: underline; }
+main { max-width: 50rem; margin: 0 auto; padding: 2rem; }
+nav + main { margin-left: 18rem; }
+h1, h2, h3, h4, h5, h6 { margin: 1em 0 0.3em; font-weight: normal; }
+p { margin: 0 0 0.5em; }
+h1, h2, h3, h4, h5, h6, p { white-space: pre-wrap; overflow-wrap: break-word; }
+mark { background: yellow; color: inherit; }
+@media (max-width: 60rem) {
+  nav { position: static; width: auto; border-right: none; border-bottom: 1px solid #ddd; }
+  nav + main { margin-left: auto; }
+}
+@media print {
+  nav { display: none; }
+  nav + main { margin-left: 0; }
+}
+";
+
+/// Escapes text for html content and attribute values.
+pub(crate) fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// h1 for the top level, down to h6 for level 5 and below.
+fn heading_tag(outline_level: Option<u32>) -> Option<String> {
+    outline_level.map(|level| format!("h{}", level.min(5) + 1))
+}
+
+fn push_run(html: &mut String, run: &Run) {
+    let style = &run.style;
+    let mut close = Vec::new();
+    if let Some(size) = style.size {
+        // sizes are in half points
+        let _ = write!(html, r#"<span style="font-size: {}pt">"#, size as f32 / 2.0);
+        close.push("</span>");
+    }
+    for (on, open_tag, close_tag) in [
+        (style.bold, "<b>", "</b>"),
+        (style.underline, "<u>", "</u>"),
+        (style.highlight, "<mark>", "</mark>"),
+    ] {
+        if on == Some(true) {
+            html.push_str(open_tag);
+            close.push(close_tag);
+        }
+    }
+    html.push_str(&escape_html(&run.text));
+    for close_tag in close.iter().rev() {
+        html.push_str(close_tag);
+    }
+}
+
+pub(crate) fn push_para(html: &mut String, para: &Para) {
+    let tag = heading_tag(para.outline_level);
+    match &tag {
+        // headings are linked to from the outline
+        Some(tag) => {
+            let _ = write!(html, r#"<{} id="p{}">"#, tag, para.index);
+        }
+        None => html.push_str("<p>"),
+    }
+    if para.runs.is_empty() {
+        // keep blank lines, an empty paragraph would collapse
+        html.push_str("<br>");
+    }
+    for run in para.runs.iter() {
+        push_run(html, run);
+    }
+    let _ = writeln!(html, "</{}>", tag.as_deref().unwrap_or("p"));
+}
+
+impl Export<'_> {
+    /// A standalone html page, with the css inlined and the outline as a
+    /// table of contents.
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+        html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+        html.push_str("<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n");
+        let _ = writeln!(html, "<title>{}</title>", escape_html(&self.title));
+        let _ = writeln!(html, "<style>\n{}</style>\n</head>\n<body>", CSS);
+        if !self.outline_paras.is_empty() {
+            html.push_str("<nav>\n");
+            self.push_contents(&mut html);
+            html.push_str("</nav>\n");
+        }
+        html.push_str("<main>\n");
+        for para in self.paras.iter() {
+            push_para(&mut html, para);
+        }
+        html.push_str("</main>\n</body>\n</html>\n");
+        html
+    }
+    /// Nested lists of links to each heading. Skipped levels don't get empty
+    /// lists, a level 3 under a level 1 is nested once.
+    fn push_contents(&self, html: &mut String) {
+        let mut open_levels: Vec<u32> = Vec::new();
+        for para in self.outline_paras.iter() {
+            let level = para.outline_level.unwrap_or(0);
+            while let Some(&open_level) = open_levels.last() {
+                if open_level <= level {
+                    break;
+                }
+                html.push_str("</li></ul>\n");
+                open_levels.pop();
+            }
+            if open_levels.last() == Some(&level) {
+                html.push_str("</li>\n");
+            } else {
+                html.push_str("<ul>\n");
+                open_levels.push(level);
+            }
+            let text: String = para.runs.iter().map(|run| run.text.as_str()).collect();
+            let _ = write!(
+                html,
+                r##"<li><a href="#p{}">{}</a>"##,
+                para.link,
+                escape_html(text.trim())
+            );
+        }
+        for _ in open_levels {
+            html.push_str("</li></ul>\n");
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use crate::document::{OutlinePara, Para, Run};
+use crate::export::Export;
+use crate::metadata::DocumentInfo;
+
+use serde::Serialize;
+
+/// The version of the json written by [`Export::to_json`]. It goes up when a
+/// field is removed or its meaning changes, not when one is added. The schema
+/// is in `schema/document.schema.json`.
+pub const JSON_VERSION: u32 = 1;
+
+// the written json, kept apart from the types the parser and cache use so
+// they can change without breaking other tools
+#[derive(Serialize)]
+struct JsonDocument<'a> {
+    schema: &'static str,
+    version: u32,
+    title: &'a str,
+    info: &'a DocumentInfo,
+    paras: Vec<JsonPara<'a>>,
+    outline: Vec<JsonHeading>,
+}
+
+#[derive(Serialize)]
+struct JsonPara<'a> {
+    index: usize,
+    outline_level: Option<u32>,
+    runs: Vec<JsonRun<'a>>,
+}
+
+// run formatting with styles, inherited formatting and toggles applied, so
+// readers don't have to
+#[derive(Serialize)]
+struct JsonRun<'a> {
+    text: &'a str,
+    bold: bool,
+    underline: bool,
+    highlight: bool,
+    size: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct JsonHeading {
+    index: usize,
+    para: usize,
+    outline_level: Option<u32>,
+    text: String,
+}
+
+fn json_run(run: &Run) -> JsonRun<'_> {
+    JsonRun {
+        text: &run.text,
+        bold: run.style.bold == Some(true),
+        underline: run.style.underline == Some(true),
+        highlight: run.style.highlight == Some(true),
+        size: run.style.size,
+    }
+}
+
+fn json_para(para: &Para) -> JsonPara<'_> {
+    JsonPara {
+        index: para.index,
+        outline_level: para.outline_level,
+        runs: para.runs.iter().map(json_run).collect(),
+    }
+}
+
+fn json_heading(para: &OutlinePara) -> JsonHeading {
+    JsonHeading {
+        index: para.index,
+        para: para.link,
+        outline_level: para.outline_level,
+        text: para.runs.iter().map(|run| run.text.as_str()).collect(),
+    }
+}
+
+impl Export<'_> {
+    /// The paragraphs, outline and `info` as versioned json, for other tools
+    /// to read without parsing the document themselves. See [`JSON_VERSION`].
+    pub fn to_json(&self, info: &DocumentInfo) -> String {
+        let document = JsonDocument {
+            schema: "docx-reader/document",
+            version: JSON_VERSION,
+            title: &self.title,
+            info,
+            paras: self.paras.iter().map(json_para).collect(),
+            outline: self.outline_paras.iter().map(json_heading).collect(),
+        };
+        // only strings, numbers and derived structs, which always serialize
+        serde_json::to_string_pretty(&document).unwrap()
+    }
+}
@@ -0,0 +1,45 @@
+//! Reads the text, formatting and outline of word processor documents.
+//!
+//! Supported formats are docx (including password protected and damaged
+//! files), Flat OPC and Word 2003 XML, OpenDocument Text and RTF. The format is
+//! detected from the contents, not the file name.
+//!
+//! ```no_run
+//! use docx_parser::Document;
+//!
+//! let mut doc = Document::new();
+//! doc.load_file("speech.docx").unwrap();
+//! for para in &doc.outline_paras {
+//!     let indent = "  ".repeat(para.outline_level.unwrap_or(0) as usize);
+//!     let text: String = para.runs.iter().map(|run| run.text.as_str()).collect();
+//!     println!("{}{}", indent, text);
+//! }
+//! ```
+
+mod clipboard;
+mod crypto;
+mod document;
+mod docx;
+mod epub;
+mod export;
+mod flat_xml;
+mod helvetica;
+mod html;
+mod json;
+mod markdown;
+mod metadata;
+mod odt;
+mod package;
+mod pdf;
+mod rtf;
+mod search;
+
+pub use clipboard::Clipboard;
+pub use document::{Attr, Document, LoadError, OutlinePara, Para, Run, Style, PARSER_VERSION};
+pub use docx::{DocxWriter, WriteError};
+pub use export::Export;
+pub use json::JSON_VERSION;
+pub use markdown::Highlight;
+pub use metadata::{CustomProperty, DocumentInfo};
+pub use pdf::{PageSetup, PaperSize};
+pub use search::{Matcher, Query, QueryError, Search, SearchResult, Snippet};
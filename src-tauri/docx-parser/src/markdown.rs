@@ -0,0 +1,157 @@
+use crate::document::{Para, Run};
+use crate::export::Export;
+
+use serde::{Deserialize, Serialize};
+
+/// How highlighted text is written, since markdown has no standard syntax
+/// for it.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Highlight {
+    /// `==text==`, for Obsidian, Typora and markdown-it with the mark plugin.
+    Mark,
+    /// `<mark>text</mark>`, which GitHub and most other renderers show.
+    Html,
+}
+
+// text with its bold, underline and highlight, in the order they nest
+struct Span<'a> {
+    text: &'a str,
+    formats: [bool; 3],
+}
+
+fn escape_markdown(text: &str, highlight: Highlight) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '~' | '|' | '&' => {
+                escaped.push('\\');
+            }
+            '=' if highlight == Highlight::Mark => escaped.push('\\'),
+            _ => {}
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// groups neighbouring spans that share a format, so bold across two runs with
+// different underlines is one **...** instead of two that run together
+fn push_spans(markdown: &mut String, spans: &[Span], format: usize, highlight: Highlight) {
+    if format == 3 {
+        for span in spans {
+            markdown.push_str(&escape_markdown(span.text, highlight));
+        }
+        return;
+    }
+    let mut start = 0;
+    while start < spans.len() {
+        let on = spans[start].formats[format];
+        let len = spans[start..]
+            .iter()
+            .position(|span| span.formats[format] != on)
+            .unwrap_or(spans.len() - start);
+        let group = &spans[start..start + len];
+        start += len;
+        if !on {
+            push_spans(markdown, group, format + 1, highlight);
+            continue;
+        }
+        let mut inner = String::new();
+        push_spans(&mut inner, group, format + 1, highlight);
+        // markers next to spaces aren't read as formatting, so keep the
+        // spaces outside
+        let trimmed = inner.trim();
+        if trimmed.is_empty() {
+            markdown.push_str(&inner);
+            continue;
+        }
+        let (open, close) = match (format, highlight) {
+            (0, _) => ("**", "**"),
+            (1, _) => ("<u>", "</u>"),
+            (_, Highlight::Mark) => ("==", "=="),
+            (_, Highlight::Html) => ("<mark>", "</mark>"),
+        };
+        let leading = &inner[..inner.len() - inner.trim_start().len()];
+        let trailing = &inner[inner.trim_end().len()..];
+        markdown.push_str(leading);
+        markdown.push_str(open);
+        markdown.push_str(trimmed);
+        markdown.push_str(close);
+        markdown.push_str(trailing);
+    }
+}
+
+fn push_runs(markdown: &mut String, runs: &[Run], in_heading: bool, highlight: Highlight) {
+    let spans: Vec<Span> = runs
+        .iter()
+        .map(|run| Span {
+            text: &run.text,
+            formats: [
+                // headings are already bold
+                run.style.bold == Some(true) && !in_heading,
+                run.style.underline == Some(true),
+                run.style.highlight == Some(true),
+            ],
+        })
+        .collect();
+    push_spans(markdown, &spans, 0, highlight);
+}
+
+// text that starts a line can be read as a heading, list or code block
+fn escape_line_start(line: &str) -> String {
+    let line = line.trim();
+    if line.starts_with(['#', '-', '+']) {
+        return format!("\\{}", line);
+    }
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    match line[digits..].chars().next() {
+        Some('.') | Some(')') if digits > 0 => {
+            format!("{}\\{}", &line[..digits], &line[digits..])
+        }
+        _ => line.to_string(),
+    }
+}
+
+fn push_para(markdown: &mut String, para: &Para, highlight: Highlight) {
+    let mut line = String::new();
+    push_runs(
+        &mut line,
+        &para.runs,
+        para.outline_level.is_some(),
+        highlight,
+    );
+    // blank lines between paragraphs are how markdown separates them anyway
+    if line.trim().is_empty() {
+        return;
+    }
+    if !markdown.is_empty() {
+        markdown.push('\n');
+    }
+    match para.outline_level {
+        Some(level) => {
+            let mut line = line.trim().to_string();
+            // a heading's closing #s are dropped
+            if line.ends_with('#') {
+                line.insert(line.len() - 1, '\\');
+            }
+            markdown.push_str(&"#".repeat(level.min(5) as usize + 1));
+            markdown.push(' ');
+            markdown.push_str(&line);
+        }
+        None => markdown.push_str(&escape_line_start(&line)),
+    }
+    markdown.push('\n');
+}
+
+impl Export<'_> {
+    /// Markdown, with outline levels as `#` headings, bold as `**` and
+    /// underline as `<u>`. Empty paragraphs are dropped.
+    pub fn to_markdown(&self, highlight: Highlight) -> String {
+        let mut markdown = String::new();
+        for para in self.paras.iter() {
+            push_para(&mut markdown, para, highlight);
+        }
+        markdown
+    }
+}
@@ -0,0 +1,128 @@
+use crate::document::Document;
+use crate::package::Package;
+
+use serde::{Deserialize, Serialize};
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+/// A user defined property, from File > Properties > Custom in word.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CustomProperty {
+    pub name: String,
+    pub value: String,
+}
+/// Properties from docProps/core.xml, app.xml and custom.xml (or meta.xml in odt).
+/// Anything the file doesn't say is `None`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct DocumentInfo {
+    pub title: Option<String>,
+    pub creator: Option<String>,
+    pub last_modified_by: Option<String>,
+    /// Kept as written, usually W3CDTF like `2022-08-01T12:00:00Z`.
+    pub created: Option<String>,
+    pub modified: Option<String>,
+    pub revision: Option<String>,
+    pub pages: Option<u32>,
+    pub words: Option<u32>,
+    pub application: Option<String>,
+    pub custom: Vec<CustomProperty>,
+}
+
+impl DocumentInfo {
+    /// The title from the properties, or `fallback` (usually the file name)
+    /// when there isn't one. Every export picks its title this way.
+    pub fn title_or<'a>(&'a self, fallback: &'a str) -> &'a str {
+        match &self.title {
+            Some(title) if !title.trim().is_empty() => title,
+            _ => fallback,
+        }
+    }
+}
+
+impl Document {
+    pub(crate) fn load_doc_props(&mut self, package: &mut Package) {
+        // every part is optional, so a missing one isn't worth a warning
+        let mut warnings = Vec::new();
+        if let Some(contents) = package.read_part("docProps/core.xml", &mut warnings) {
+            self.read_props(&contents);
+        }
+        if let Some(contents) = package.read_part("docProps/app.xml", &mut warnings) {
+            self.read_props(&contents);
+        }
+        if let Some(contents) = package.read_part("docProps/custom.xml", &mut warnings) {
+            self.read_props(&contents);
+        }
+        if let Some(contents) = package.read_part("meta.xml", &mut warnings) {
+            self.read_props(&contents);
+        }
+    }
+    // the parts don't share element names, so one reader handles all of them
+    pub(crate) fn read_props(&mut self, contents: &str) {
+        let mut reader = Reader::from_str(contents);
+        let mut buf = Vec::new();
+        let mut current: Option<Vec<u8>> = None;
+        let mut custom_name: Option<String> = None;
+        // in odt, dc:creator is whoever saved last
+        let is_odt = contents.contains("<office:document-meta");
+        loop {
+            match reader.read_event(&mut buf) {
+                Ok(Event::Start(ref e)) => {
+                    if e.name() == b"property" || e.name() == b"meta:user-defined" {
+                        custom_name = Self::get_attr(&reader, e, b"name")
+                            .or_else(|| Self::get_attr(&reader, e, b"meta:name"));
+                    }
+                    current = Some(e.name().to_vec());
+                }
+                Ok(Event::Empty(ref e)) if e.name() == b"meta:document-statistic" => {
+                    let count = |name: &[u8]| {
+                        Self::get_attr(&reader, e, name).and_then(|count| count.parse().ok())
+                    };
+                    self.info.pages = count(b"meta:page-count").or(self.info.pages);
+                    self.info.words = count(b"meta:word-count").or(self.info.words);
+                }
+                Ok(Event::Text(e)) => {
+                    let text = e.unescape_and_decode(&reader).unwrap_or_default();
+                    let text = text.trim().to_string();
+                    if !text.is_empty() {
+                        self.set_prop(current.as_deref(), &mut custom_name, is_odt, text);
+                    }
+                }
+                Ok(Event::End(_)) => current = None,
+                Ok(Event::Eof) => break,
+                // metadata is a nice to have, don't bother recovering it
+                Err(_) => break,
+                _ => (),
+            }
+            buf.clear();
+        }
+    }
+    fn set_prop(
+        &mut self,
+        element: Option<&[u8]>,
+        custom_name: &mut Option<String>,
+        is_odt: bool,
+        text: String,
+    ) {
+        let info = &mut self.info;
+        match element {
+            Some(b"dc:title") => info.title = Some(text),
+            Some(b"dc:creator") if is_odt => info.last_modified_by = Some(text),
+            Some(b"dc:creator") | Some(b"meta:initial-creator") => info.creator = Some(text),
+            Some(b"cp:lastModifiedBy") => info.last_modified_by = Some(text),
+            Some(b"dcterms:created") | Some(b"meta:creation-date") => info.created = Some(text),
+            Some(b"dcterms:modified") | Some(b"dc:date") => info.modified = Some(text),
+            Some(b"cp:revision") | Some(b"meta:editing-cycles") => info.revision = Some(text),
+            Some(b"Pages") => info.pages = text.parse().ok(),
+            Some(b"Words") => info.words = text.parse().ok(),
+            Some(b"Application") | Some(b"meta:generator") => info.application = Some(text),
+            // custom values are wrapped in a vt: element naming their type
+            Some(name) if name.starts_with(b"vt:") || name == b"meta:user-defined" => {
+                if let Some(name) = custom_name.take() {
+                    info.custom.push(CustomProperty { name, value: text });
+                }
+            }
+            _ => (),
+        }
+    }
+}
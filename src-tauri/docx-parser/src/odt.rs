@@ -0,0 +1,300 @@
+use crate::document::{Attr, Document, Para, Run, Style};
+use crate::package::Package;
+
+use std::collections::HashMap;
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+// a style as written in the file, before its parents are applied
+struct OdtStyle {
+    parent: Option<String>,
+    attr: Attr,
+}
+
+// key of the default paragraph style (style:default-style) in the style map
+const DEFAULT_STYLE: &str = "";
+// odf has 10 outline levels, starting at 1
+const MAX_OUTLINE_LEVEL: u32 = 10;
+// word only has 9, so odf's level 10 is read as word's last level
+const MAX_WORD_OUTLINE_LEVEL: u32 = 8;
+// text:s counts are only ever a few spaces, don't let a bad one allocate gigabytes
+const MAX_SPACES: usize = 1000;
+
+impl Document {
+    pub(crate) fn load_odt(&mut self, package: &mut Package) {
+        let mut styles = HashMap::new();
+        // styles.xml has the common styles, content.xml the automatic ones
+        if let Some(contents) = package.read_part("styles.xml", &mut self.warnings) {
+            self.read_odt_styles(&contents, &mut styles);
+        }
+        let contents = match package.read_part("content.xml", &mut self.warnings) {
+            Some(contents) => contents,
+            None => {
+                self.warnings
+                    .push("content.xml is missing, the text was lost".to_string());
+                return;
+            }
+        };
+        self.read_odt_styles(&contents, &mut styles);
+        self.resolve_odt_styles(&styles);
+        self.load_odt_paras(&contents);
+    }
+    fn read_odt_styles(&mut self, contents: &str, styles: &mut HashMap<String, OdtStyle>) {
+        let mut reader = Reader::from_str(contents);
+        let mut buf = Vec::new();
+        let mut current: Option<(String, OdtStyle)> = None;
+        loop {
+            let event = reader.read_event(&mut buf);
+            let is_empty = matches!(event, Ok(Event::Empty(_)));
+            match event {
+                Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) => match e.name() {
+                    b"style:style" | b"style:default-style" => {
+                        let name = match e.name() {
+                            b"style:style" => Self::get_attr(&reader, e, b"style:name"),
+                            _ => match Self::get_attr(&reader, e, b"style:family").as_deref() {
+                                Some("paragraph") => Some(DEFAULT_STYLE.to_string()),
+                                _ => None,
+                            },
+                        };
+                        let outline_level =
+                            Self::get_attr(&reader, e, b"style:default-outline-level")
+                                .and_then(|level| Self::parse_odt_outline_level(&level));
+                        let style = OdtStyle {
+                            parent: Self::get_attr(&reader, e, b"style:parent-style-name"),
+                            attr: Attr {
+                                style: Style {
+                                    bold: None,
+                                    underline: None,
+                                    highlight: None,
+                                    size: None,
+                                },
+                                outline_level,
+                            },
+                        };
+                        match name {
+                            Some(name) if is_empty => {
+                                styles.insert(name, style);
+                            }
+                            Some(name) => current = Some((name, style)),
+                            None => (),
+                        }
+                    }
+                    b"style:text-properties" => {
+                        if let Some((_, style)) = current.as_mut() {
+                            Self::read_odt_text_properties(&reader, e, &mut style.attr.style);
+                        }
+                    }
+                    _ => (),
+                },
+                Ok(Event::End(ref e))
+                    if e.name() == b"style:style" || e.name() == b"style:default-style" =>
+                {
+                    if let Some((name, style)) = current.take() {
+                        styles.insert(name, style);
+                    }
+                }
+                Ok(Event::Eof) => break,
+                Err(e) => {
+                    self.warnings.push(format!(
+                        "styles are damaged at position {} ({:?}), later styles were lost",
+                        reader.buffer_position(),
+                        e
+                    ));
+                    break;
+                }
+                _ => (),
+            }
+            buf.clear();
+        }
+    }
+    fn parse_odt_outline_level(level: &str) -> Option<u32> {
+        level
+            .parse::<u32>()
+            .ok()
+            .filter(|level| *level > 0 && *level <= MAX_OUTLINE_LEVEL)
+            // odf levels start at 1, word's at 0
+            .map(|level| (level - 1).min(MAX_WORD_OUTLINE_LEVEL))
+    }
+    fn read_odt_text_properties(reader: &Reader<&[u8]>, e: &BytesStart, style: &mut Style) {
+        if let Some(weight) = Self::get_attr(reader, e, b"fo:font-weight") {
+            style.bold = Some(match weight.as_str() {
+                "bold" | "bolder" => true,
+                "normal" | "lighter" => false,
+                weight => weight.parse::<u32>().map_or(false, |weight| weight >= 600),
+            });
+        }
+        if let Some(underline) = Self::get_attr(reader, e, b"style:text-underline-style") {
+            style.underline = Some(underline != "none");
+        }
+        if let Some(background) = Self::get_attr(reader, e, b"fo:background-color") {
+            style.highlight = Some(background != "transparent");
+        }
+        if let Some(size) = Self::get_attr(reader, e, b"fo:font-size") {
+            // sizes are stored in half points, like w:sz
+            if let Some(points) = size.strip_suffix("pt") {
+                if let Ok(points) = points.parse::<f32>() {
+                    style.size = Some((points * 2.0).round() as u32);
+                }
+            }
+        }
+    }
+    // flatten parent styles into self.style_map, so lookups don't need to follow chains
+    fn resolve_odt_styles(&mut self, styles: &HashMap<String, OdtStyle>) {
+        for name in styles.keys() {
+            let mut chain = vec![name];
+            let mut parent = styles[name].parent.as_ref();
+            while let Some(parent_name) = parent {
+                // guard against styles that are their own ancestor
+                if chain.contains(&parent_name) || !styles.contains_key(parent_name) {
+                    break;
+                }
+                chain.push(parent_name);
+                parent = styles[parent_name].parent.as_ref();
+            }
+            let mut attr = Attr {
+                style: Style {
+                    bold: None,
+                    underline: None,
+                    highlight: None,
+                    size: None,
+                },
+                outline_level: None,
+            };
+            for name in chain.iter().rev() {
+                let style = &styles[*name];
+                Self::mutate_style(&mut attr.style, &style.attr.style);
+                attr.outline_level = style.attr.outline_level.or(attr.outline_level);
+            }
+            self.style_map.insert(name.clone(), attr);
+        }
+    }
+    fn odt_style(&self, base: &Style, name: Option<String>) -> Style {
+        let mut style = base.clone();
+        if let Some(attr) = name.and_then(|name| self.style_map.get(&name)) {
+            Self::mutate_style(&mut style, &attr.style);
+        }
+        style
+    }
+    fn load_odt_paras(&mut self, contents: &str) {
+        let mut reader = Reader::from_str(contents);
+        let mut buf = Vec::new();
+
+        let default_style = self.odt_style(
+            &Style {
+                bold: None,
+                underline: None,
+                highlight: None,
+                size: None,
+            },
+            Some(DEFAULT_STYLE.to_string()),
+        );
+        let mut current_para: Option<Para> = None;
+        // style of each open text:span, with the paragraph style at the bottom
+        let mut style_stack: Vec<Style> = Vec::new();
+        // notes, comments and frames have their own paragraphs, skip them
+        let mut skip_depth = 0;
+        loop {
+            match reader.read_event(&mut buf) {
+                Ok(Event::Start(ref e)) if skip_depth > 0 => {
+                    if matches!(
+                        e.name(),
+                        b"text:note" | b"office:annotation" | b"draw:frame"
+                    ) {
+                        skip_depth += 1;
+                    }
+                }
+                Ok(Event::End(ref e)) if skip_depth > 0 => {
+                    if matches!(
+                        e.name(),
+                        b"text:note" | b"office:annotation" | b"draw:frame"
+                    ) {
+                        skip_depth -= 1;
+                    }
+                }
+                Ok(Event::Start(ref e)) => match e.name() {
+                    b"text:note" | b"office:annotation" | b"draw:frame" => skip_depth += 1,
+                    b"text:p" | b"text:h" => {
+                        let style_name = Self::get_attr(&reader, e, b"text:style-name");
+                        let mut outline_level = style_name
+                            .as_ref()
+                            .and_then(|name| self.style_map.get(name))
+                            .and_then(|attr| attr.outline_level);
+                        if e.name() == b"text:h" {
+                            // a heading without a level of its own takes its
+                            // style's, and is a top level heading without either
+                            outline_level = Self::get_attr(&reader, e, b"text:outline-level")
+                                .and_then(|level| Self::parse_odt_outline_level(&level))
+                                .or(outline_level)
+                                .or(Some(0));
+                        }
+                        style_stack = vec![self.odt_style(&default_style, style_name)];
+                        current_para = Some(Para {
+                            index: 0,
+                            outline_level,
+                            runs: Vec::new(),
+                        });
+                    }
+                    b"text:span" => {
+                        let base = style_stack.last().unwrap_or(&default_style).clone();
+                        let style_name = Self::get_attr(&reader, e, b"text:style-name");
+                        style_stack.push(self.odt_style(&base, style_name));
+                    }
+                    _ => (),
+                },
+                // text:s stands for text:c spaces
+                Ok(Event::Empty(ref e)) if skip_depth == 0 && e.name() == b"text:s" => {
+                    if let (Some(para), Some(style)) = (current_para.as_mut(), style_stack.last()) {
+                        let count = Self::get_attr(&reader, e, b"text:c")
+                            .and_then(|count| count.parse::<usize>().ok())
+                            .unwrap_or(1)
+                            .min(MAX_SPACES);
+                        para.push_run(Run {
+                            text: " ".repeat(count),
+                            style: style.clone(),
+                        });
+                    }
+                }
+                Ok(Event::End(ref e)) => match e.name() {
+                    b"text:p" | b"text:h" => {
+                        if let Some(para) = current_para.take() {
+                            self.push_para(para);
+                        }
+                        style_stack.clear();
+                    }
+                    // never pop the paragraph style
+                    b"text:span" if style_stack.len() > 1 => {
+                        style_stack.pop();
+                    }
+                    _ => (),
+                },
+                Ok(Event::Text(e)) if skip_depth == 0 => {
+                    if let (Some(para), Some(style)) = (current_para.as_mut(), style_stack.last()) {
+                        let text = e
+                            .unescape_and_decode(&reader)
+                            .unwrap_or_else(|_| String::from_utf8_lossy(&e).into_owned());
+                        // replace whitespace, like in docx runs
+                        let text = text.replace("\n", "").replace("\r", "").replace("\t", "");
+                        para.push_run(Run {
+                            text,
+                            style: style.clone(),
+                        });
+                    }
+                }
+                Ok(Event::Eof) => break,
+                Err(e) => {
+                    // keep every paragraph before the error
+                    self.warnings.push(format!(
+                        "document is damaged at position {} ({:?}), paragraphs after paragraph {} were lost",
+                        reader.buffer_position(),
+                        e,
+                        self.paras.len()
+                    ));
+                    break;
+                }
+                _ => (),
+            }
+            buf.clear();
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+
+use flate2::read::DeflateDecoder;
+use zip::read::ZipArchive;
+
+const LOCAL_HEADER_SIGNATURE: &[u8] = b"PK\x03\x04";
+const LOCAL_HEADER_LENGTH: usize = 30;
+// general purpose flag meaning sizes are only known after the data
+const DATA_DESCRIPTOR_FLAG: u16 = 1 << 3;
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+
+// the parts of a zip package (docx or odt), read through the central directory,
+// or recovered from the local file headers when the central directory is broken
+pub enum Package<'a> {
+    Zip(ZipArchive<Cursor<&'a [u8]>>),
+    Recovered(HashMap<String, Vec<u8>>),
+}
+
+impl<'a> Package<'a> {
+    pub fn open(bytes: &'a [u8], warnings: &mut Vec<String>) -> Package<'a> {
+        match ZipArchive::new(Cursor::new(bytes)) {
+            Ok(archive) => Package::Zip(archive),
+            Err(e) => {
+                warnings.push(format!(
+                    "the file is damaged ({}), recovered what could be read",
+                    e
+                ));
+                Package::Recovered(Self::recover(bytes, warnings))
+            }
+        }
+    }
+    pub fn has_part(&mut self, name: &str) -> bool {
+        match self {
+            Package::Zip(archive) => archive.by_name(name).is_ok(),
+            Package::Recovered(parts) => parts.contains_key(name),
+        }
+    }
+    // every part's name, in the order they're stored
+    pub fn part_names(&mut self) -> Vec<String> {
+        match self {
+            Package::Zip(archive) => (0..archive.len())
+                .filter_map(|i| {
+                    archive
+                        .by_index_raw(i)
+                        .ok()
+                        .map(|file| file.name().to_string())
+                })
+                .collect(),
+            Package::Recovered(parts) => {
+                let mut names: Vec<String> = parts.keys().cloned().collect();
+                names.sort();
+                names
+            }
+        }
+    }
+    // the part as text, or None if it's missing. a part that can only be read
+    // partially is returned as far as it goes
+    pub fn read_part(&mut self, name: &str, warnings: &mut Vec<String>) -> Option<String> {
+        let bytes = self.read_bytes(name, warnings)?;
+        Some(String::from_utf8_lossy(&bytes).into_owned())
+    }
+    // like read_part, for binary parts like images
+    pub fn read_bytes(&mut self, name: &str, warnings: &mut Vec<String>) -> Option<Vec<u8>> {
+        let bytes = match self {
+            Package::Zip(archive) => {
+                let mut file = archive.by_name(name).ok()?;
+                let mut bytes = Vec::new();
+                if let Err(e) = read_as_far_as_possible(&mut file, &mut bytes) {
+                    warnings.push(format!("{} is damaged ({}), it was cut short", name, e));
+                }
+                bytes
+            }
+            Package::Recovered(parts) => parts.get(name)?.clone(),
+        };
+        Some(bytes)
+    }
+    // walk the local file headers in order, ignoring the central directory
+    fn recover(bytes: &[u8], warnings: &mut Vec<String>) -> HashMap<String, Vec<u8>> {
+        let mut parts = HashMap::new();
+        let mut pos = 0;
+        while let Some(offset) = find(&bytes[pos..], LOCAL_HEADER_SIGNATURE) {
+            let start = pos + offset;
+            pos = start + LOCAL_HEADER_SIGNATURE.len();
+            let header = match bytes.get(start..start + LOCAL_HEADER_LENGTH) {
+                Some(header) => header,
+                None => break,
+            };
+            let u16_at = |i: usize| u16::from_le_bytes([header[i], header[i + 1]]);
+            let u32_at = |i: usize| {
+                u32::from_le_bytes([header[i], header[i + 1], header[i + 2], header[i + 3]])
+            };
+            let flags = u16_at(6);
+            let method = u16_at(8);
+            let compressed_size = u32_at(18) as usize;
+            let name_end = start + LOCAL_HEADER_LENGTH + u16_at(26) as usize;
+            let data_start = name_end + u16_at(28) as usize;
+            let name = match bytes.get(start + LOCAL_HEADER_LENGTH..name_end) {
+                Some(name) => String::from_utf8_lossy(name).into_owned(),
+                None => break,
+            };
+            if data_start > bytes.len() {
+                warnings.push(format!("{} was lost", name));
+                break;
+            }
+            // without a size, the data runs until the next header
+            let known_size = flags & DATA_DESCRIPTOR_FLAG == 0 || compressed_size != 0;
+            let data_end = if known_size {
+                data_start + compressed_size
+            } else {
+                find(&bytes[data_start..], LOCAL_HEADER_SIGNATURE)
+                    .map_or(bytes.len(), |offset| data_start + offset)
+            };
+            let data = &bytes[data_start..data_end.min(bytes.len())];
+            let mut contents = Vec::new();
+            let result = match method {
+                STORED => {
+                    contents.extend_from_slice(data);
+                    Ok(())
+                }
+                DEFLATED => read_as_far_as_possible(&mut DeflateDecoder::new(data), &mut contents),
+                _ => {
+                    warnings.push(format!("{} uses an unknown compression and was lost", name));
+                    continue;
+                }
+            };
+            if result.is_err() || data_end > bytes.len() {
+                warnings.push(format!("{} is damaged, it was cut short", name));
+            }
+            if known_size {
+                pos = data_end.min(bytes.len()).max(pos);
+            }
+            parts.insert(name, contents);
+        }
+        parts
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+// like read_to_end, but keeps what was read before an error
+fn read_as_far_as_possible<R: Read>(reader: &mut R, bytes: &mut Vec<u8>) -> std::io::Result<()> {
+    let mut buf = [0; 8192];
+    loop {
+        match reader.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(n) => bytes.extend_from_slice(&buf[..n]),
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(e),
+        }
+    }
+}
@@ -0,0 +1,419 @@
+use crate::document::{Para, Run};
+use crate::export::Export;
+use crate::helvetica;
+
+use std::collections::HashMap;
+use std::io::Write;
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use pdf_writer::types::PageMode;
+use pdf_writer::{Content, Filter, Finish, Name, Pdf, Rect, Ref, Str, TextStr};
+use serde::{Deserialize, Serialize};
+
+/// Paper size and margins for [`Export::to_pdf`], in points (1/72 inch).
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PageSetup {
+    pub width: f32,
+    pub height: f32,
+    pub margin_top: f32,
+    pub margin_right: f32,
+    pub margin_bottom: f32,
+    pub margin_left: f32,
+}
+impl PageSetup {
+    /// US Letter with one inch margins.
+    pub const LETTER: PageSetup = PageSetup {
+        width: 612.0,
+        height: 792.0,
+        margin_top: 72.0,
+        margin_right: 72.0,
+        margin_bottom: 72.0,
+        margin_left: 72.0,
+    };
+    /// A4 with 2.5 cm margins.
+    pub const A4: PageSetup = PageSetup {
+        width: 595.28,
+        height: 841.89,
+        margin_top: 70.87,
+        margin_right: 70.87,
+        margin_bottom: 70.87,
+        margin_left: 70.87,
+    };
+
+    /// The same paper with `margin` on every side. `None` if that leaves no
+    /// room for text.
+    pub fn with_margin(self, margin: f32) -> Option<PageSetup> {
+        let room = |length: f32| length - 2.0 * margin > 0.0;
+        if !(margin >= 0.0 && room(self.width) && room(self.height)) {
+            return None;
+        }
+        Some(PageSetup {
+            margin_top: margin,
+            margin_right: margin,
+            margin_bottom: margin,
+            margin_left: margin,
+            ..self
+        })
+    }
+}
+impl Default for PageSetup {
+    fn default() -> PageSetup {
+        PageSetup::LETTER
+    }
+}
+
+/// A named paper size, for callers that pick one instead of giving
+/// measurements.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PaperSize {
+    Letter,
+    A4,
+}
+impl PaperSize {
+    pub fn page_setup(self) -> PageSetup {
+        match self {
+            PaperSize::Letter => PageSetup::LETTER,
+            PaperSize::A4 => PageSetup::A4,
+        }
+    }
+}
+
+// font size for runs without one, the same as the app
+const DEFAULT_SIZE: f32 = 12.0;
+const LINE_HEIGHT: f32 = 1.2;
+// extra space after each paragraph and before each heading, in points
+const PARA_SPACING: f32 = 2.0;
+const HEADING_SPACING: f32 = 8.0;
+// from Helvetica's AFM, as fractions of the font size
+const ASCENT: f32 = 0.718;
+const DESCENT: f32 = 0.207;
+const UNDERLINE_POSITION: f32 = -0.1;
+const UNDERLINE_THICKNESS: f32 = 0.05;
+
+const REGULAR: Name = Name(b"F1");
+const BOLD: Name = Name(b"F2");
+
+#[derive(Clone, Copy, PartialEq)]
+struct FragmentStyle {
+    size: f32,
+    bold: bool,
+    underline: bool,
+    highlight: bool,
+}
+impl FragmentStyle {
+    fn new(run: &Run) -> FragmentStyle {
+        FragmentStyle {
+            // sizes are in half points
+            size: run
+                .style
+                .size
+                .map_or(DEFAULT_SIZE, |size| size as f32 / 2.0),
+            bold: run.style.bold == Some(true),
+            underline: run.style.underline == Some(true),
+            highlight: run.style.highlight == Some(true),
+        }
+    }
+}
+
+// WinAnsiEncoding text in one style, at `x` from the start of its line
+struct Fragment {
+    text: Vec<u8>,
+    x: f32,
+    width: f32,
+    style: FragmentStyle,
+}
+
+#[derive(Default)]
+struct Line {
+    fragments: Vec<Fragment>,
+    width: f32,
+}
+impl Line {
+    fn push(&mut self, code: u8, style: FragmentStyle) {
+        let width = helvetica::width(code, style.bold) * style.size;
+        match self.fragments.last_mut() {
+            Some(fragment) if fragment.style == style => {
+                fragment.text.push(code);
+                fragment.width += width;
+            }
+            _ => self.fragments.push(Fragment {
+                text: vec![code],
+                x: self.width,
+                width,
+                style,
+            }),
+        }
+        self.width += width;
+    }
+    // spaces at the end of a line aren't drawn, so they don't get underlined
+    fn trim_end(&mut self) {
+        while let Some(fragment) = self.fragments.last_mut() {
+            while fragment.text.last() == Some(&b' ') {
+                fragment.text.pop();
+                let width = helvetica::width(b' ', fragment.style.bold) * fragment.style.size;
+                fragment.width -= width;
+                self.width -= width;
+            }
+            if !fragment.text.is_empty() {
+                break;
+            }
+            self.fragments.pop();
+        }
+    }
+    fn size(&self) -> f32 {
+        self.fragments
+            .iter()
+            .map(|fragment| fragment.style.size)
+            .fold(0.0, f32::max)
+    }
+}
+
+// breaks a paragraph into lines no wider than `max_width`, at spaces when it
+// can and anywhere in a word that's wider than a whole line
+fn lay_out(para: &Para, max_width: f32) -> Vec<Line> {
+    let mut lines = vec![Line::default()];
+    for run in para.runs.iter() {
+        let style = FragmentStyle::new(run);
+        let codes: Vec<u8> = run.text.chars().map(helvetica::win_ansi).collect();
+        for word in codes.split_inclusive(|&code| code == b' ') {
+            let width: f32 = word
+                .iter()
+                .filter(|&&code| code != b' ')
+                .map(|&code| helvetica::width(code, style.bold) * style.size)
+                .sum();
+            let line = lines.last_mut().unwrap();
+            if line.width + width > max_width && !line.fragments.is_empty() {
+                line.trim_end();
+                lines.push(Line::default());
+            }
+            for &code in word {
+                let line = lines.last_mut().unwrap();
+                if code == b' ' && line.fragments.is_empty() {
+                    continue;
+                }
+                let width = helvetica::width(code, style.bold) * style.size;
+                if code != b' ' && line.width + width > max_width && !line.fragments.is_empty() {
+                    lines.push(Line::default());
+                }
+                lines.last_mut().unwrap().push(code, style);
+            }
+        }
+    }
+    lines.last_mut().unwrap().trim_end();
+    lines
+}
+
+fn draw_line(content: &mut Content, line: &Line, left: f32, baseline: f32) {
+    // highlights go under the text
+    for fragment in line.fragments.iter() {
+        let style = fragment.style;
+        if style.highlight {
+            content.set_fill_rgb(1.0, 1.0, 0.0);
+            content.rect(
+                left + fragment.x,
+                baseline - style.size * DESCENT,
+                fragment.width,
+                style.size * (ASCENT + DESCENT),
+            );
+            content.fill_nonzero();
+        }
+    }
+    content.set_fill_gray(0.0);
+    content.begin_text();
+    for fragment in line.fragments.iter() {
+        let style = fragment.style;
+        content.set_font(if style.bold { BOLD } else { REGULAR }, style.size);
+        content.set_text_matrix([1.0, 0.0, 0.0, 1.0, left + fragment.x, baseline]);
+        content.show(Str(&fragment.text));
+    }
+    content.end_text();
+    for fragment in line.fragments.iter() {
+        let style = fragment.style;
+        if style.underline {
+            content.rect(
+                left + fragment.x,
+                baseline + style.size * UNDERLINE_POSITION,
+                fragment.width,
+                style.size * UNDERLINE_THICKNESS,
+            );
+            content.fill_nonzero();
+        }
+    }
+}
+
+fn compress(data: &[u8]) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    // writing to a Vec can't fail
+    let _ = encoder.write_all(data);
+    encoder.finish().unwrap_or_default()
+}
+
+// a heading in the pdf's outline, with its place on the page
+struct Bookmark {
+    title: String,
+    page: usize,
+    top: f32,
+    parent: Option<usize>,
+    children: Vec<usize>,
+}
+
+impl Export<'_> {
+    /// A pdf with the outline as bookmarks. It uses the Helvetica fonts every
+    /// pdf reader has, so characters outside of Windows-1252 show up as `?`.
+    pub fn to_pdf(&self, page: &PageSetup) -> Vec<u8> {
+        let max_width = page.width - page.margin_left - page.margin_right;
+        let page_top = page.height - page.margin_top;
+        let mut pages = vec![Content::new()];
+        // top of the next line
+        let mut y = page_top;
+        // where each heading starts, by paragraph index
+        let mut positions = HashMap::new();
+        for para in self.paras.iter() {
+            if para.outline_level.is_some() && y < page_top {
+                y -= HEADING_SPACING;
+            }
+            for (i, line) in lay_out(para, max_width).iter().enumerate() {
+                let size = match line.size() {
+                    size if size > 0.0 => size,
+                    // an empty paragraph is a blank line
+                    _ => DEFAULT_SIZE,
+                };
+                let height = size * LINE_HEIGHT;
+                if y - height < page.margin_bottom && y < page_top {
+                    pages.push(Content::new());
+                    y = page_top;
+                }
+                if i == 0 {
+                    positions.insert(para.index, (pages.len() - 1, y));
+                }
+                // center the text in the line
+                let baseline = y - (height + size * (ASCENT - DESCENT)) / 2.0;
+                draw_line(pages.last_mut().unwrap(), line, page.margin_left, baseline);
+                y -= height;
+            }
+            y -= PARA_SPACING;
+        }
+
+        let mut bookmarks: Vec<Bookmark> = Vec::new();
+        let mut top_level = Vec::new();
+        let mut open: Vec<(u32, usize)> = Vec::new();
+        for para in self.outline_paras.iter() {
+            let (page, top) = match positions.get(&para.link) {
+                Some(&position) => position,
+                None => continue,
+            };
+            let level = para.outline_level.unwrap_or(0);
+            while open
+                .last()
+                .map_or(false, |&(open_level, _)| open_level >= level)
+            {
+                open.pop();
+            }
+            let parent = open.last().map(|&(_, parent)| parent);
+            let index = bookmarks.len();
+            match parent {
+                Some(parent) => bookmarks[parent].children.push(index),
+                None => top_level.push(index),
+            }
+            let title: String = para.runs.iter().map(|run| run.text.as_str()).collect();
+            bookmarks.push(Bookmark {
+                title: title.trim().to_string(),
+                page,
+                top,
+                parent,
+                children: Vec::new(),
+            });
+            open.push((level, index));
+        }
+
+        let mut pdf = Pdf::new();
+        let mut next_id = Ref::new(1);
+        let catalog_id = next_id.bump();
+        let page_tree_id = next_id.bump();
+        let regular_id = next_id.bump();
+        let bold_id = next_id.bump();
+        let info_id = next_id.bump();
+        let outline_id = next_id.bump();
+        let page_ids: Vec<Ref> = pages.iter().map(|_| next_id.bump()).collect();
+        let content_ids: Vec<Ref> = pages.iter().map(|_| next_id.bump()).collect();
+        let bookmark_ids: Vec<Ref> = bookmarks.iter().map(|_| next_id.bump()).collect();
+
+        let mut catalog = pdf.catalog(catalog_id);
+        catalog.pages(page_tree_id);
+        if !bookmarks.is_empty() {
+            catalog.outlines(outline_id);
+            catalog.page_mode(PageMode::UseOutlines);
+        }
+        catalog.finish();
+        pdf.document_info(info_id).title(TextStr(&self.title));
+        pdf.pages(page_tree_id)
+            .kids(page_ids.iter().copied())
+            .count(page_ids.len() as i32);
+        for (id, name) in [(regular_id, "Helvetica"), (bold_id, "Helvetica-Bold")] {
+            pdf.type1_font(id)
+                .base_font(Name(name.as_bytes()))
+                .encoding_predefined(Name(b"WinAnsiEncoding"));
+        }
+        for (i, content) in pages.into_iter().enumerate() {
+            let mut pdf_page = pdf.page(page_ids[i]);
+            pdf_page
+                .media_box(Rect::new(0.0, 0.0, page.width, page.height))
+                .parent(page_tree_id)
+                .contents(content_ids[i]);
+            pdf_page
+                .resources()
+                .fonts()
+                .pair(REGULAR, regular_id)
+                .pair(BOLD, bold_id);
+            pdf_page.finish();
+            let data = compress(&content.finish());
+            pdf.stream(content_ids[i], &data)
+                .filter(Filter::FlateDecode);
+        }
+
+        if let (Some(&first), Some(&last)) = (top_level.first(), top_level.last()) {
+            pdf.outline(outline_id)
+                .first(bookmark_ids[first])
+                .last(bookmark_ids[last])
+                .count(top_level.len() as i32);
+        }
+        let mut prev = vec![None; bookmarks.len()];
+        let mut next = vec![None; bookmarks.len()];
+        for siblings in std::iter::once(&top_level).chain(bookmarks.iter().map(|b| &b.children)) {
+            for pair in siblings.windows(2) {
+                next[pair[0]] = Some(pair[1]);
+                prev[pair[1]] = Some(pair[0]);
+            }
+        }
+        for (i, bookmark) in bookmarks.iter().enumerate() {
+            let mut item = pdf.outline_item(bookmark_ids[i]);
+            item.title(TextStr(&bookmark.title));
+            item.parent(
+                bookmark
+                    .parent
+                    .map_or(outline_id, |parent| bookmark_ids[parent]),
+            );
+            if let Some(prev) = prev[i] {
+                item.prev(bookmark_ids[prev]);
+            }
+            if let Some(next) = next[i] {
+                item.next(bookmark_ids[next]);
+            }
+            if let (Some(&first), Some(&last)) =
+                (bookmark.children.first(), bookmark.children.last())
+            {
+                item.first(bookmark_ids[first]);
+                item.last(bookmark_ids[last]);
+                // negative means closed, big files would have a huge outline
+                item.count(-(bookmark.children.len() as i32));
+            }
+            item.dest()
+                .page(page_ids[bookmark.page])
+                .xyz(page.margin_left, bookmark.top, None);
+            item.finish();
+        }
+        pdf.finish()
+    }
+}
@@ -0,0 +1,454 @@
+use crate::document::{Attr, Document, Para, Run, Style};
+
+use std::collections::HashMap;
+
+#[derive(Debug, PartialEq)]
+pub enum Token<'a> {
+    GroupStart,
+    GroupEnd,
+    ControlWord(&'a str, Option<i32>),
+    ControlSymbol(u8),
+    // a \'hh escape, the byte is still in the document's code page
+    Hex(u8),
+    Text(&'a [u8]),
+}
+
+pub struct Tokenizer<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Tokenizer<'a> {
+    pub fn new(bytes: &'a [u8]) -> Tokenizer<'a> {
+        Tokenizer { bytes, pos: 0 }
+    }
+    fn read_control(&mut self) -> Option<Token<'a>> {
+        // skip the backslash
+        self.pos += 1;
+        let first = *self.bytes.get(self.pos)?;
+        if !first.is_ascii_alphabetic() {
+            self.pos += 1;
+            if first == b'\'' {
+                let hex = self.bytes.get(self.pos..self.pos + 2)?;
+                self.pos += 2;
+                let hex = std::str::from_utf8(hex).ok()?;
+                return Some(Token::Hex(u8::from_str_radix(hex, 16).unwrap_or(b'?')));
+            }
+            return Some(Token::ControlSymbol(first));
+        }
+        let start = self.pos;
+        while self.pos < self.bytes.len() && self.bytes[self.pos].is_ascii_alphabetic() {
+            self.pos += 1;
+        }
+        let name = std::str::from_utf8(&self.bytes[start..self.pos]).unwrap();
+        let param_start = self.pos;
+        if self.bytes.get(self.pos) == Some(&b'-') {
+            self.pos += 1;
+        }
+        while self.pos < self.bytes.len() && self.bytes[self.pos].is_ascii_digit() {
+            self.pos += 1;
+        }
+        let param = std::str::from_utf8(&self.bytes[param_start..self.pos])
+            .unwrap()
+            .parse::<i32>()
+            .ok();
+        // a single space only delimits the control word
+        if self.bytes.get(self.pos) == Some(&b' ') {
+            self.pos += 1;
+        }
+        Some(Token::ControlWord(name, param))
+    }
+}
+
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = Token<'a>;
+    fn next(&mut self) -> Option<Token<'a>> {
+        loop {
+            let byte = *self.bytes.get(self.pos)?;
+            match byte {
+                b'{' => {
+                    self.pos += 1;
+                    return Some(Token::GroupStart);
+                }
+                b'}' => {
+                    self.pos += 1;
+                    return Some(Token::GroupEnd);
+                }
+                b'\\' => return self.read_control(),
+                // line breaks in the source don't mean anything
+                b'\r' | b'\n' => self.pos += 1,
+                _ => {
+                    let start = self.pos;
+                    while self.pos < self.bytes.len()
+                        && !matches!(self.bytes[self.pos], b'{' | b'}' | b'\\' | b'\r' | b'\n')
+                    {
+                        self.pos += 1;
+                    }
+                    return Some(Token::Text(&self.bytes[start..self.pos]));
+                }
+            }
+        }
+    }
+}
+
+// groups starting with these hold no document text
+const SKIPPED_DESTINATIONS: &[&str] = &[
+    "fonttbl",
+    "colortbl",
+    "info",
+    "pict",
+    "header",
+    "headerl",
+    "headerr",
+    "headerf",
+    "footer",
+    "footerl",
+    "footerr",
+    "footerf",
+    "footnote",
+    "annotation",
+    "object",
+    "listtable",
+    "listoverridetable",
+    "listtext",
+    "pntext",
+    "rsidtbl",
+    "generator",
+    "themedata",
+    "colorschememapping",
+    "latentstyles",
+    "datastore",
+    "xmlnstbl",
+    "revtbl",
+    "filetbl",
+];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Destination {
+    Text,
+    Skip,
+    Stylesheet,
+    StyleDef,
+}
+
+#[derive(Clone)]
+struct GroupState {
+    destination: Destination,
+    // explicit character formatting, applied over the paragraph style
+    char_style: Style,
+    para_style: Style,
+    outline_level: Option<u32>,
+    // how many fallback characters follow a \u escape
+    uc: usize,
+}
+
+// a stylesheet entry, keyed like "s1" or "cs10" in the style map
+struct StyleDef {
+    key: String,
+    based_on: Option<String>,
+    attr: Attr,
+}
+
+fn empty_style() -> Style {
+    Style {
+        bold: None,
+        underline: None,
+        highlight: None,
+        size: None,
+    }
+}
+
+// windows-1252 differs from latin-1 only in 0x80..0xa0
+const CP1252_HIGH: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8d}', 'Ž', '\u{8f}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9d}', 'ž', 'Ÿ',
+];
+fn decode_cp1252(byte: u8) -> char {
+    match byte {
+        0x80..=0x9f => CP1252_HIGH[(byte - 0x80) as usize],
+        _ => byte as char,
+    }
+}
+
+struct Interpreter<'d> {
+    doc: &'d mut Document,
+    stack: Vec<GroupState>,
+    state: GroupState,
+    para: Para,
+    style_def: Option<StyleDef>,
+    style_defs: Vec<StyleDef>,
+    // fallback characters left to skip after a \u escape
+    skip_chars: usize,
+    // first half of a utf-16 surrogate pair written as two \u escapes
+    high_surrogate: Option<u32>,
+}
+
+impl<'d> Interpreter<'d> {
+    fn push_text(&mut self, text: &str) {
+        if self.state.destination != Destination::Text {
+            return;
+        }
+        let mut style = self.state.para_style.clone();
+        Document::mutate_style(&mut style, &self.state.char_style);
+        self.para.push_run(Run {
+            text: text.to_string(),
+            style,
+        });
+    }
+    fn push_char(&mut self, c: char) {
+        let mut buf = [0; 4];
+        self.push_text(c.encode_utf8(&mut buf));
+    }
+    fn end_para(&mut self) {
+        if self.state.destination != Destination::Text {
+            return;
+        }
+        let para = std::mem::replace(
+            &mut self.para,
+            Para {
+                index: 0,
+                outline_level: None,
+                runs: Vec::new(),
+            },
+        );
+        self.doc.push_para(Para {
+            outline_level: self.state.outline_level,
+            ..para
+        });
+    }
+    fn apply_para_style(&mut self, key: &str) {
+        let mut style = empty_style();
+        let mut outline_level = None;
+        if let Some(attr) = self.doc.style_map.get(key) {
+            style = attr.style.clone();
+            outline_level = attr.outline_level;
+        }
+        self.state.para_style = style;
+        self.state.outline_level = outline_level;
+    }
+    fn control_word(&mut self, name: &str, param: Option<i32>) {
+        // formatting inside a stylesheet entry describes that style
+        let defining = self.state.destination == Destination::StyleDef;
+        let on = param != Some(0);
+        match name {
+            "b" => self.state.char_style.bold = Some(on),
+            "ulnone" => self.state.char_style.underline = Some(false),
+            // ulc is the underline colour, every other ul* is a kind of underline
+            "ulc" => (),
+            name if name.starts_with("ul") => self.state.char_style.underline = Some(on),
+            "highlight" => self.state.char_style.highlight = Some(on),
+            "fs" => self.state.char_style.size = param.map(|size| size.max(0) as u32),
+            "plain" => self.state.char_style = empty_style(),
+            "outlinelevel" => {
+                self.state.outline_level =
+                    param.and_then(|level| Document::parse_outline_level(&level.to_string()))
+            }
+            "s" | "cs" | "ds" | "ts" if defining => {
+                if let Some(def) = self.style_def.as_mut() {
+                    def.key = format!("{}{}", name, param.unwrap_or(0));
+                }
+            }
+            "sbasedon" if defining => {
+                if let Some(def) = self.style_def.as_mut() {
+                    def.based_on = param.map(|param| format!("s{}", param));
+                }
+            }
+            "s" => self.apply_para_style(&format!("s{}", param.unwrap_or(0))),
+            "cs" => {
+                if let Some(attr) = self.doc.style_map.get(&format!("cs{}", param.unwrap_or(0))) {
+                    Document::mutate_style(&mut self.state.char_style, &attr.style);
+                }
+            }
+            "pard" => self.apply_para_style("s0"),
+            "par" | "cell" => self.end_para(),
+            "uc" => self.state.uc = param.unwrap_or(1).max(0) as usize,
+            "u" => {
+                let mut code = param.unwrap_or(0);
+                // values above 32767 are written as negative numbers
+                if code < 0 {
+                    code += 65536;
+                }
+                let code = code as u32;
+                match (self.high_surrogate.take(), code) {
+                    (_, 0xd800..=0xdbff) => self.high_surrogate = Some(code),
+                    (Some(high), 0xdc00..=0xdfff) => {
+                        let c = 0x10000 + ((high - 0xd800) << 10) + (code - 0xdc00);
+                        self.push_char(char::from_u32(c).unwrap_or('\u{fffd}'));
+                    }
+                    (_, code) => self.push_char(char::from_u32(code).unwrap_or('\u{fffd}')),
+                }
+                self.skip_chars = self.state.uc;
+            }
+            "emdash" => self.push_char('—'),
+            "endash" => self.push_char('–'),
+            "lquote" => self.push_char('‘'),
+            "rquote" => self.push_char('’'),
+            "ldblquote" => self.push_char('“'),
+            "rdblquote" => self.push_char('”'),
+            "bullet" => self.push_char('•'),
+            _ => (),
+        }
+    }
+    fn group_start(&mut self, tokens: &mut std::iter::Peekable<Tokenizer>) {
+        self.stack.push(self.state.clone());
+        self.skip_chars = 0;
+        match self.state.destination {
+            Destination::Skip => return,
+            Destination::Stylesheet => {
+                self.state.destination = Destination::StyleDef;
+                self.state.char_style = empty_style();
+                self.state.outline_level = None;
+                self.style_def = Some(StyleDef {
+                    key: "s0".to_string(),
+                    based_on: None,
+                    attr: Attr {
+                        style: empty_style(),
+                        outline_level: None,
+                    },
+                });
+                return;
+            }
+            _ => (),
+        }
+        match tokens.peek() {
+            Some(Token::ControlWord("stylesheet", _)) => {
+                self.state.destination = Destination::Stylesheet;
+            }
+            Some(Token::ControlWord(name, _)) if SKIPPED_DESTINATIONS.contains(name) => {
+                self.state.destination = Destination::Skip;
+            }
+            // \* marks a destination that readers may ignore, and we do
+            Some(Token::ControlSymbol(b'*')) if self.state.destination == Destination::Text => {
+                self.state.destination = Destination::Skip;
+            }
+            _ => (),
+        }
+    }
+    fn group_end(&mut self) {
+        if self.state.destination == Destination::StyleDef
+            && self.stack.last().map(|state| state.destination) == Some(Destination::Stylesheet)
+        {
+            if let Some(mut def) = self.style_def.take() {
+                def.attr.style = self.state.char_style.clone();
+                def.attr.outline_level = self.state.outline_level;
+                self.style_defs.push(def);
+            }
+        }
+        let leaving_stylesheet = self.state.destination == Destination::Stylesheet;
+        if let Some(state) = self.stack.pop() {
+            self.state = state;
+        }
+        if leaving_stylesheet {
+            self.resolve_styles();
+            self.apply_para_style("s0");
+        }
+        self.skip_chars = 0;
+    }
+    // flatten \sbasedon chains into the document's style map
+    fn resolve_styles(&mut self) {
+        let defs: HashMap<&str, &StyleDef> = self
+            .style_defs
+            .iter()
+            .map(|def| (def.key.as_str(), def))
+            .collect();
+        for def in self.style_defs.iter() {
+            let mut chain = vec![def];
+            let mut based_on = def.based_on.as_deref();
+            while let Some(key) = based_on {
+                match defs.get(key) {
+                    Some(parent) if !chain.iter().any(|def| def.key == parent.key) => {
+                        chain.push(parent);
+                        based_on = parent.based_on.as_deref();
+                    }
+                    _ => break,
+                }
+            }
+            let mut attr = Attr {
+                style: empty_style(),
+                outline_level: None,
+            };
+            for def in chain.iter().rev() {
+                Document::mutate_style(&mut attr.style, &def.attr.style);
+                attr.outline_level = def.attr.outline_level.or(attr.outline_level);
+            }
+            self.doc.style_map.insert(def.key.clone(), attr);
+        }
+    }
+    fn text(&mut self, bytes: &[u8]) {
+        let mut text = String::with_capacity(bytes.len());
+        for &byte in bytes {
+            if self.skip_chars > 0 {
+                self.skip_chars -= 1;
+                continue;
+            }
+            // tabs are dropped like in docx runs
+            if byte != b'\t' {
+                text.push(decode_cp1252(byte));
+            }
+        }
+        self.push_text(&text);
+    }
+}
+
+impl Document {
+    /// Loads an RTF document.
+    pub fn load_rtf(&mut self, bytes: &[u8]) {
+        let mut interpreter = Interpreter {
+            doc: self,
+            stack: Vec::new(),
+            state: GroupState {
+                destination: Destination::Text,
+                char_style: empty_style(),
+                para_style: empty_style(),
+                outline_level: None,
+                uc: 1,
+            },
+            para: Para {
+                index: 0,
+                outline_level: None,
+                runs: Vec::new(),
+            },
+            style_def: None,
+            style_defs: Vec::new(),
+            skip_chars: 0,
+            high_surrogate: None,
+        };
+        let mut tokens = Tokenizer::new(bytes).peekable();
+        while let Some(token) = tokens.next() {
+            match token {
+                Token::GroupStart => interpreter.group_start(&mut tokens),
+                Token::GroupEnd => interpreter.group_end(),
+                Token::ControlWord(name, param) => {
+                    if name != "u" {
+                        interpreter.skip_chars = 0;
+                    }
+                    interpreter.control_word(name, param);
+                }
+                Token::Hex(byte) => {
+                    if interpreter.skip_chars > 0 {
+                        interpreter.skip_chars -= 1;
+                    } else {
+                        interpreter.push_char(decode_cp1252(byte));
+                    }
+                }
+                Token::ControlSymbol(symbol) => {
+                    interpreter.skip_chars = 0;
+                    match symbol {
+                        b'\\' | b'{' | b'}' => interpreter.push_char(symbol as char),
+                        b'~' => interpreter.push_char('\u{a0}'),
+                        b'_' => interpreter.push_char('\u{2011}'),
+                        // an escaped line break is the same as \par
+                        b'\n' | b'\r' => interpreter.end_para(),
+                        _ => (),
+                    }
+                }
+                Token::Text(bytes) => interpreter.text(bytes),
+            }
+        }
+        // keep the last paragraph even without a closing \par
+        if !interpreter.para.runs.is_empty() {
+            interpreter.state.destination = Destination::Text;
+            interpreter.end_para();
+        }
+    }
+}
@@ -0,0 +1,580 @@
+use crate::document::{Para, Run};
+
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::fmt;
+use std::ops::Range;
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// What to look for in a document.
+///
+/// Text queries match however the document writes the same thing: ligatures
+/// like "ﬁ" are their letters, curly quotes are straight quotes, dashes are
+/// hyphens, and without `match_case` "STRASSE" matches "straße".
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Query {
+    pub text: String,
+    pub match_case: bool,
+    /// Only match headings.
+    pub only_outline: bool,
+    /// `text` is a regular expression in the syntax of the regex crate, like
+    /// `\b(19|20)\d\d\b` for years. It's matched against the paragraph text
+    /// as written.
+    #[serde(default)]
+    pub regex: bool,
+    /// Only match whole words, so "war" doesn't match "warrant".
+    #[serde(default)]
+    pub whole_word: bool,
+    /// Match letters with or without accents, so "cafe" matches "café". Not
+    /// for regular expressions.
+    #[serde(default)]
+    pub ignore_diacritics: bool,
+}
+impl Query {
+    /// Gets the query ready to search with. Only fails for an invalid
+    /// regular expression.
+    pub fn matcher(&self) -> Result<Matcher, QueryError> {
+        let fold = Fold {
+            case: !self.match_case,
+            diacritics: self.ignore_diacritics,
+        };
+        let pattern = if self.regex {
+            let regex = RegexBuilder::new(&self.text)
+                .case_insensitive(!self.match_case)
+                .build()
+                .map_err(QueryError::Regex)?;
+            Pattern::Regex(regex)
+        } else {
+            Pattern::Text(fold.fold(&self.text).text.into_owned())
+        };
+        Ok(Matcher {
+            only_outline: self.only_outline,
+            whole_word: self.whole_word,
+            fold,
+            pattern,
+        })
+    }
+}
+
+/// Why a [`Query`] can't be searched for.
+#[derive(Debug)]
+pub enum QueryError {
+    /// The text isn't a valid regular expression. The message points at
+    /// where in the pattern it went wrong.
+    Regex(regex::Error),
+}
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            QueryError::Regex(e) => write!(f, "{}", e),
+        }
+    }
+}
+impl std::error::Error for QueryError {}
+
+/// A [`Query`] ready to search with, from [`Query::matcher`].
+#[derive(Clone, Debug)]
+pub struct Matcher {
+    only_outline: bool,
+    whole_word: bool,
+    fold: Fold,
+    pattern: Pattern,
+}
+#[derive(Clone, Debug)]
+enum Pattern {
+    // the query text, folded like the paragraph text
+    Text(String),
+    Regex(Regex),
+}
+impl Pattern {
+    // the first match at or after byte `start`
+    fn find_at(&self, haystack: &str, start: usize) -> Option<Range<usize>> {
+        match self {
+            Pattern::Text(needle) => haystack[start..]
+                .find(needle.as_str())
+                .map(|i| start + i..start + i + needle.len()),
+            Pattern::Regex(regex) => regex.find_at(haystack, start).map(|m| m.range()),
+        }
+    }
+}
+impl Matcher {
+    // whether every paragraph `next` matches is one this matches too, so
+    // narrowing this one's results finds all of next's
+    fn covers(&self, next: &Matcher) -> bool {
+        let (needle, next_needle) = match (&self.pattern, &next.pattern) {
+            // nothing matches an empty query, so it can't be narrowed
+            (Pattern::Text(needle), _) if needle.is_empty() => return false,
+            (Pattern::Text(needle), Pattern::Text(next_needle)) => (needle, next_needle),
+            // a longer regular expression can match more
+            _ => return false,
+        };
+        // folding case finds the same and more, ignoring accents finds
+        // different letters too
+        if self.fold.diacritics != next.fold.diacritics || (next.fold.case && !self.fold.case) {
+            return false;
+        }
+        // next's needle, folded like the paragraphs this searches. case
+        // folding goes character by character, so wherever next finds its
+        // needle this finds the folded needle
+        let next_needle = match self.fold.case == next.fold.case {
+            true => Cow::Borrowed(next_needle.as_str()),
+            false => self.fold.fold(next_needle).text,
+        };
+        (!self.only_outline || next.only_outline)
+            // "war" as a whole word isn't in "warrant"
+            && !self.whole_word
+            && next_needle.contains(needle.as_str())
+    }
+    /// The non-overlapping matches in a paragraph, as ranges of characters in
+    /// its text. `para_text` is the paragraph's [`Para::text`], passed in so
+    /// callers can keep it around between searches. Empty matches are left
+    /// out.
+    pub fn find(&self, para: &Para, para_text: &str) -> Vec<Range<usize>> {
+        if self.only_outline && para.outline_level.is_none() {
+            return Vec::new();
+        }
+        match &self.pattern {
+            Pattern::Text(needle) if needle.is_empty() => Vec::new(),
+            Pattern::Text(_) => {
+                // found in the folded text, then mapped back to the characters
+                // they were folded from
+                let folded = self.fold.fold(para_text);
+                let bytes = self.find_bytes(&folded.text);
+                char_ranges(&folded.text, bytes)
+                    .into_iter()
+                    .map(|range| folded.origin(range))
+                    .collect()
+            }
+            Pattern::Regex(_) => char_ranges(para_text, self.find_bytes(para_text)),
+        }
+    }
+    fn find_bytes(&self, haystack: &str) -> Vec<Range<usize>> {
+        let mut matches = Vec::new();
+        let mut start = 0;
+        while start <= haystack.len() {
+            let range = match self.pattern.find_at(haystack, start) {
+                Some(range) => range,
+                None => break,
+            };
+            if range.is_empty() || (self.whole_word && !is_whole_word(haystack, &range)) {
+                // try again from the next character, which a shorter or later
+                // match could start at
+                start = range.start
+                    + haystack[range.start..]
+                        .chars()
+                        .next()
+                        .map_or(1, char::len_utf8);
+                continue;
+            }
+            start = range.end;
+            matches.push(range);
+        }
+        matches
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || is_combining_mark(c)
+}
+
+// like the regex \b, but only checked on the sides of the match that start or
+// end with a word character, so "'98" can still be a whole word
+fn is_whole_word(haystack: &str, range: &Range<usize>) -> bool {
+    let matched = &haystack[range.clone()];
+    let before = haystack[..range.start].chars().next_back();
+    let after = haystack[range.end..].chars().next();
+    let starts_word = matched.chars().next().map_or(false, is_word_char);
+    let ends_word = matched.chars().next_back().map_or(false, is_word_char);
+    let joins_before = starts_word && before.map_or(false, is_word_char);
+    let joins_after = ends_word && after.map_or(false, is_word_char);
+    !joins_before && !joins_after
+}
+
+// how text is normalised before a text query is matched against it
+#[derive(Clone, Copy, Debug)]
+struct Fold {
+    case: bool,
+    diacritics: bool,
+}
+// folded text, and for each of its characters the characters of the original
+// it came from
+struct Folded<'a> {
+    text: Cow<'a, str>,
+    // None when each character folded to one in its place
+    origins: Option<Vec<Range<usize>>>,
+}
+impl Folded<'_> {
+    // the characters of the original that a range of folded characters came from
+    fn origin(&self, range: Range<usize>) -> Range<usize> {
+        match &self.origins {
+            Some(origins) => origins[range.start].start..origins[range.end - 1].end,
+            None => range,
+        }
+    }
+}
+impl Fold {
+    fn fold<'a>(&self, text: &'a str) -> Folded<'a> {
+        // most paragraphs, and nothing to normalise
+        if text.is_ascii() {
+            let text = match self.case {
+                true => Cow::Owned(text.to_ascii_lowercase()),
+                false => Cow::Borrowed(text),
+            };
+            return Folded {
+                text,
+                origins: None,
+            };
+        }
+        let mut folded = String::with_capacity(text.len());
+        let mut origins = Vec::with_capacity(text.len());
+        let mut cluster = String::new();
+        let mut chars = text.chars().peekable();
+        let mut start = 0;
+        while let Some(c) = chars.next() {
+            let has_marks = chars.peek().map_or(false, |&mark| is_combining_mark(mark));
+            if !has_marks {
+                let simple = match c.is_ascii() {
+                    true if self.case => Some(c.to_ascii_lowercase()),
+                    true => Some(c),
+                    false => fold_punctuation(c),
+                };
+                if let Some(c) = simple {
+                    folded.push(c);
+                    origins.push(start..start + 1);
+                    start += 1;
+                    continue;
+                }
+            }
+            // a letter and the accents on it are folded together, so neither
+            // half of one can be matched alone
+            cluster.clear();
+            cluster.push(c);
+            while let Some(&mark) = chars.peek() {
+                if !is_combining_mark(mark) {
+                    break;
+                }
+                cluster.push(mark);
+                chars.next();
+            }
+            let end = start + cluster.chars().count();
+            let mut push = |c: char| {
+                folded.push(c);
+                origins.push(start..end);
+            };
+            if self.diacritics {
+                for c in cluster.nfkd().filter(|&c| !is_combining_mark(c)) {
+                    self.fold_char(c, &mut push);
+                }
+            } else {
+                for c in cluster.nfkc() {
+                    self.fold_char(c, &mut push);
+                }
+            }
+            start = end;
+        }
+        Folded {
+            text: Cow::Owned(folded),
+            origins: Some(origins),
+        }
+    }
+    fn fold_char(&self, c: char, push: &mut impl FnMut(char)) {
+        let c = fold_punctuation(c).unwrap_or(c);
+        if !self.case {
+            push(c);
+            return;
+        }
+        // full case folding, where it's more than lowercasing
+        match c {
+            'ß' | 'ẞ' => {
+                push('s');
+                push('s');
+            }
+            'ς' => push('σ'),
+            c => c.to_lowercase().for_each(push),
+        }
+    }
+}
+
+// curly quotes and dashes, which are already normalised
+fn fold_punctuation(c: char) -> Option<char> {
+    match c {
+        '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}' | '\u{2032}' => Some('\''),
+        '\u{201C}' | '\u{201D}' | '\u{201E}' | '\u{201F}' | '\u{00AB}' | '\u{00BB}' => Some('"'),
+        '\u{2010}'..='\u{2015}' | '\u{2212}' => Some('-'),
+        _ => None,
+    }
+}
+
+// byte ranges in order to character ranges, in one pass over the text
+fn char_ranges(text: &str, bytes: Vec<Range<usize>>) -> Vec<Range<usize>> {
+    let mut chars = text.char_indices().map(|(i, _)| i).enumerate().peekable();
+    let mut to_char = |byte: usize| {
+        while let Some(&(_, i)) = chars.peek() {
+            if i >= byte {
+                break;
+            }
+            chars.next();
+        }
+        chars.peek().map_or(text.chars().count(), |&(c, _)| c)
+    };
+    bytes
+        .into_iter()
+        .map(|range| {
+            let start = to_char(range.start);
+            start..to_char(range.end)
+        })
+        .collect()
+}
+
+/// One match. A paragraph with several matches has a result for each.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SearchResult {
+    /// Index of the paragraph in [`Document::paras`](crate::Document::paras).
+    pub link: usize,
+    /// Position in the results.
+    pub index: usize,
+    /// Which match in the paragraph this is, starting at 0. It's the same as
+    /// the position in [`Search::find`] for the paragraph.
+    pub query_index: usize,
+    /// Where the match starts in the paragraph's text, in characters.
+    pub start: usize,
+    /// Where the match ends, exclusive.
+    pub end: usize,
+    /// The runs the match is in, as indexes into [`Para::runs`].
+    pub runs: Range<usize>,
+    pub snippet: Snippet,
+}
+
+/// The part of a paragraph around a match, to show in a list of results.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Snippet {
+    /// The paragraph's runs, cut down to the snippet.
+    pub runs: Vec<Run>,
+    /// Where the snippet starts in the paragraph's text, in characters.
+    pub start: usize,
+    /// Whether there's more of the paragraph before the snippet.
+    pub cut_start: bool,
+    /// Whether there's more of the paragraph after it.
+    pub cut_end: bool,
+}
+
+// characters of context kept before and after a match in a snippet, about
+// what fits in the three lines the results show
+const SNIPPET_BEFORE: usize = 40;
+const SNIPPET_AFTER: usize = 160;
+
+// a match as it's kept between searches, without its snippet
+#[derive(Clone, Debug)]
+struct Match {
+    link: usize,
+    query_index: usize,
+    range: Range<usize>,
+}
+impl Match {
+    fn result(&self, index: usize, para: &Para, para_text: &str) -> SearchResult {
+        let chars: Vec<char> = para_text.chars().collect();
+        // cut at spaces, so the snippet doesn't start or end mid word
+        let mut start = self.range.start.saturating_sub(SNIPPET_BEFORE);
+        if start > 0 {
+            if let Some(space) = chars[start..self.range.start]
+                .iter()
+                .position(|c| c.is_whitespace())
+            {
+                start += space + 1;
+            }
+        }
+        let mut end = (self.range.end + SNIPPET_AFTER).min(chars.len());
+        if end < chars.len() {
+            if let Some(space) = chars[self.range.end..end]
+                .iter()
+                .rposition(|c| c.is_whitespace())
+            {
+                end = self.range.end + space;
+            }
+        }
+        let mut runs = Vec::new();
+        let mut spanned = para.runs.len()..0;
+        let mut run_start = 0;
+        for (i, run) in para.runs.iter().enumerate() {
+            let run_end = run_start + run.text.chars().count();
+            if run_start < self.range.end && run_end > self.range.start {
+                spanned.start = spanned.start.min(i);
+                spanned.end = i + 1;
+            }
+            let from = start.max(run_start);
+            let to = end.min(run_end);
+            if from < to {
+                runs.push(Run {
+                    text: chars[from..to].iter().collect(),
+                    style: run.style.clone(),
+                });
+            }
+            run_start = run_end;
+        }
+        SearchResult {
+            link: self.link,
+            index,
+            query_index: self.query_index,
+            start: self.range.start,
+            end: self.range.end,
+            runs: spanned,
+            snippet: Snippet {
+                runs,
+                start,
+                cut_start: start > 0,
+                cut_end: end < chars.len(),
+            },
+        }
+    }
+}
+
+/// Searches one document. Results are found lazily, only as far as they're
+/// asked for, and kept between queries so that typing more of a query narrows
+/// the old results instead of starting over. Narrowed results are always the
+/// same as a new search's.
+#[derive(Clone, Default)]
+pub struct Search {
+    /// The text of each paragraph, built once per document.
+    pub para_texts: Vec<String>,
+    last: Option<LastSearch>,
+}
+// the last query and how far its search got
+#[derive(Clone)]
+struct LastSearch {
+    query: Query,
+    matcher: Matcher,
+    // the matches found so far, in order
+    matches: Vec<Match>,
+    // every paragraph the query matches that isn't in matches yet is in
+    // candidates or at or after rest. candidates are in order and before rest
+    candidates: VecDeque<usize>,
+    rest: usize,
+}
+impl LastSearch {
+    fn new(query: &Query, matcher: Matcher) -> LastSearch {
+        LastSearch {
+            query: query.clone(),
+            matcher,
+            matches: Vec::new(),
+            candidates: VecDeque::new(),
+            rest: 0,
+        }
+    }
+    // a search for a query whose matches are all in paragraphs this one
+    // matches, which only has to look at those
+    fn narrow(&self, query: &Query, matcher: Matcher) -> LastSearch {
+        let mut candidates: VecDeque<usize> = VecDeque::new();
+        for found in self.matches.iter() {
+            if candidates.back() != Some(&found.link) {
+                candidates.push_back(found.link);
+            }
+        }
+        candidates.extend(self.candidates.iter());
+        LastSearch {
+            candidates,
+            rest: self.rest,
+            ..LastSearch::new(query, matcher)
+        }
+    }
+    // finds matches until there are at least `len` or there are no more
+    fn fill(&mut self, paras: &[Para], para_texts: &[String], len: usize) {
+        while self.matches.len() < len {
+            let link = match self.candidates.pop_front() {
+                Some(link) => link,
+                None if self.rest < paras.len() => {
+                    self.rest += 1;
+                    self.rest - 1
+                }
+                None => break,
+            };
+            let ranges = self.matcher.find(&paras[link], &para_texts[link]);
+            for (query_index, range) in ranges.into_iter().enumerate() {
+                self.matches.push(Match {
+                    link,
+                    query_index,
+                    range,
+                });
+            }
+        }
+    }
+}
+impl Search {
+    pub fn new() -> Search {
+        Search::default()
+    }
+    /// Starts over with a new document.
+    pub fn set_paras(&mut self, paras: &[Para]) {
+        self.clear();
+        self.para_texts.clear();
+        for para in paras {
+            self.para_texts.push(para.text());
+        }
+    }
+    /// Forgets the last query and its results.
+    pub fn clear(&mut self) {
+        self.last = None;
+    }
+    /// The last query searched for.
+    pub fn last_query(&self) -> Option<&Query> {
+        self.last.as_ref().map(|last| &last.query)
+    }
+    /// The matches of `query` in paragraph `link`, like [`Matcher::find`],
+    /// for highlighting them where the paragraph is shown.
+    pub fn find(
+        &self,
+        query: &Query,
+        paras: &[Para],
+        link: usize,
+    ) -> Result<Vec<Range<usize>>, QueryError> {
+        let (para, para_text) = match (paras.get(link), self.para_texts.get(link)) {
+            (Some(para), Some(para_text)) => (para, para_text),
+            _ => return Ok(Vec::new()),
+        };
+        match &self.last {
+            Some(last) if last.query == *query => Ok(last.matcher.find(para, para_text)),
+            _ => Ok(query.matcher()?.find(para, para_text)),
+        }
+    }
+    /// Results `i..j` for `query`. `paras` must be the paragraphs last passed
+    /// to [`Search::set_paras`]. An invalid query leaves the last query and
+    /// its results alone.
+    pub fn search(
+        &mut self,
+        query: &Query,
+        paras: &[Para],
+        i: usize,
+        j: usize,
+    ) -> Result<Vec<SearchResult>, QueryError> {
+        let last = match self.last.take() {
+            // the same query, so everything found so far still holds
+            Some(last) if last.query == *query => last,
+            last => {
+                let matcher = match query.matcher() {
+                    Ok(matcher) => matcher,
+                    Err(e) => {
+                        self.last = last;
+                        return Err(e);
+                    }
+                };
+                match last {
+                    Some(last) if last.matcher.covers(&matcher) => last.narrow(query, matcher),
+                    _ => LastSearch::new(query, matcher),
+                }
+            }
+        };
+        let last = self.last.insert(last);
+        last.fill(paras, &self.para_texts, j);
+        let i = i.min(last.matches.len());
+        let j = j.min(last.matches.len());
+        Ok((i..j)
+            .map(|index| {
+                let found = &last.matches[index];
+                found.result(index, &paras[found.link], &self.para_texts[found.link])
+            })
+            .collect())
+    }
+}
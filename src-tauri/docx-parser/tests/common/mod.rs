@@ -0,0 +1,190 @@
+// builds minimal docx packages in memory, so tests can describe a document in a
+// few lines of xml instead of checking in binary files
+#![allow(dead_code)]
+
+use docx_parser::{Document, Run, Style};
+
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::Path;
+
+use zip::write::{FileOptions, ZipWriter};
+
+const NAMESPACES: &str =
+    r#"xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main""#;
+
+pub struct DocxBuilder {
+    styles: Option<String>,
+    body: Option<String>,
+    parts: Vec<(String, Vec<u8>)>,
+}
+
+impl DocxBuilder {
+    pub fn new() -> DocxBuilder {
+        DocxBuilder {
+            styles: Some(String::new()),
+            body: Some(String::new()),
+            parts: Vec::new(),
+        }
+    }
+    // the contents of w:styles
+    pub fn styles(mut self, styles: &str) -> DocxBuilder {
+        self.styles = Some(styles.to_string());
+        self
+    }
+    // the contents of w:body
+    pub fn body(mut self, body: &str) -> DocxBuilder {
+        self.body = Some(body.to_string());
+        self
+    }
+    pub fn without_styles(mut self) -> DocxBuilder {
+        self.styles = None;
+        self
+    }
+    pub fn without_document(mut self) -> DocxBuilder {
+        self.body = None;
+        self
+    }
+    // any other part, e.g. docProps/core.xml
+    pub fn part(mut self, name: &str, contents: &[u8]) -> DocxBuilder {
+        self.parts.push((name.to_string(), contents.to_vec()));
+        self
+    }
+    pub fn build(&self) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = FileOptions::default();
+        if let Some(styles) = &self.styles {
+            zip.start_file("word/styles.xml", options).unwrap();
+            write!(zip, "<w:styles {}>{}</w:styles>", NAMESPACES, styles).unwrap();
+        }
+        if let Some(body) = &self.body {
+            zip.start_file("word/document.xml", options).unwrap();
+            write!(
+                zip,
+                "<w:document {}><w:body>{}</w:body></w:document>",
+                NAMESPACES, body
+            )
+            .unwrap();
+        }
+        for (name, contents) in self.parts.iter() {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(contents).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+    pub fn load(&self) -> Document {
+        let mut doc = Document::new();
+        doc.load_bytes(&self.build()).unwrap();
+        doc
+    }
+}
+
+const ODF_NAMESPACES: &str = concat!(
+    r#"xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" "#,
+    r#"xmlns:style="urn:oasis:names:tc:opendocument:xmlns:style:1.0" "#,
+    r#"xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0" "#,
+    r#"xmlns:fo="urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0""#
+);
+
+// like DocxBuilder, for opendocument text packages
+pub struct OdtBuilder {
+    styles: String,
+    automatic_styles: String,
+    body: String,
+}
+
+impl OdtBuilder {
+    pub fn new() -> OdtBuilder {
+        OdtBuilder {
+            styles: String::new(),
+            automatic_styles: String::new(),
+            body: String::new(),
+        }
+    }
+    // the contents of office:styles in styles.xml
+    pub fn styles(mut self, styles: &str) -> OdtBuilder {
+        self.styles = styles.to_string();
+        self
+    }
+    // the contents of office:automatic-styles in content.xml
+    pub fn automatic_styles(mut self, styles: &str) -> OdtBuilder {
+        self.automatic_styles = styles.to_string();
+        self
+    }
+    // the contents of office:text
+    pub fn body(mut self, body: &str) -> OdtBuilder {
+        self.body = body.to_string();
+        self
+    }
+    pub fn build(&self) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = FileOptions::default();
+        zip.start_file("mimetype", options).unwrap();
+        zip.write_all(b"application/vnd.oasis.opendocument.text")
+            .unwrap();
+        zip.start_file("styles.xml", options).unwrap();
+        write!(
+            zip,
+            "<office:document-styles {}><office:styles>{}</office:styles></office:document-styles>",
+            ODF_NAMESPACES, self.styles
+        )
+        .unwrap();
+        zip.start_file("content.xml", options).unwrap();
+        write!(
+            zip,
+            "<office:document-content {}><office:automatic-styles>{}</office:automatic-styles><office:body><office:text>{}</office:text></office:body></office:document-content>",
+            ODF_NAMESPACES, self.automatic_styles, self.body
+        )
+        .unwrap();
+        zip.finish().unwrap().into_inner()
+    }
+    pub fn load(&self) -> Document {
+        let mut doc = Document::new();
+        doc.load_bytes(&self.build()).unwrap();
+        doc
+    }
+}
+
+// a w:style element, with properties written as the contents of w:rPr
+pub fn style(id: &str, based_on: Option<&str>, run_properties: &str) -> String {
+    let based_on = based_on
+        .map(|id| format!(r#"<w:basedOn w:val="{}"/>"#, id))
+        .unwrap_or_default();
+    format!(
+        r#"<w:style w:type="paragraph" w:styleId="{}">{}<w:rPr>{}</w:rPr></w:style>"#,
+        id, based_on, run_properties
+    )
+}
+
+// a heading style, like word's built in Heading 1 (level 0)
+pub fn heading_style(id: &str, level: u32, run_properties: &str) -> String {
+    format!(
+        r#"<w:style w:type="paragraph" w:styleId="{}"><w:pPr><w:outlineLvl w:val="{}"/></w:pPr><w:rPr>{}</w:rPr></w:style>"#,
+        id, level, run_properties
+    )
+}
+
+pub fn plain() -> Style {
+    Style {
+        bold: None,
+        underline: None,
+        highlight: None,
+        size: None,
+    }
+}
+
+pub fn texts(runs: &[Run]) -> Vec<&str> {
+    runs.iter().map(|run| run.text.as_str()).collect()
+}
+
+// a file from the load_bytes fuzz seeds, which double as small real documents
+pub fn seed(name: &str) -> Vec<u8> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("fuzz/seeds/load_bytes")
+        .join(name);
+    fs::read(&path).unwrap()
+}
+
+pub fn outline_levels(doc: &Document) -> Vec<Option<u32>> {
+    doc.paras.iter().map(|para| para.outline_level).collect()
+}
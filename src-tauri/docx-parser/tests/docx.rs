@@ -0,0 +1,231 @@
+mod common;
+
+use common::{heading_style, style, DocxBuilder};
+use docx_parser::{Document, DocxWriter, WriteError};
+
+use std::io::{Cursor, Read};
+
+use zip::ZipArchive;
+
+const CONTENT_TYPES: &str = r#"<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Default Extension="png" ContentType="image/png"/><Override PartName="/word/document.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml"/><Override PartName="/word/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.styles+xml"/><Override PartName="/word/header1.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.header+xml"/><Override PartName="/word/numbering.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.numbering+xml"/></Types>"#;
+
+const PACKAGE_RELS: &str = r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="word/document.xml"/></Relationships>"#;
+
+// styles aren't pointed to from paragraphs, the image and header are
+const DOCUMENT_RELS: &str = r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/><Relationship Id="rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/image" Target="media/image1.png"/><Relationship Id="rId3" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/hyperlink" Target="https://example.com/?a=1&amp;b=2" TargetMode="External"/><Relationship Id="rId4" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/header" Target="/word/header1.xml"/><Relationship Id="rId5" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/numbering" Target="numbering.xml"/></Relationships>"#;
+
+// the first card has an image, the second a link, and a section break after
+// it with a header
+const BODY: &str = r#"<w:p><w:pPr><w:pStyle w:val="Heading1"/></w:pPr><w:r><w:t>Pocket</w:t></w:r></w:p>
+<w:p><w:pPr><w:pStyle w:val="Heading2"/></w:pPr><w:r><w:t>Hat</w:t></w:r></w:p>
+<w:p><w:pPr><w:pStyle w:val="Heading4"/></w:pPr><w:r><w:t>First tag</w:t></w:r></w:p>
+<w:p><w:r><w:rPr><w:b/></w:rPr><w:t>first card</w:t></w:r><w:r><w:drawing><a:blip r:embed="rId2"/></w:drawing></w:r></w:p>
+<w:p><w:pPr><w:pStyle w:val="Heading4"/></w:pPr><w:r><w:t>Second tag</w:t></w:r></w:p>
+<w:tbl><w:tr><w:tc><w:p><w:hyperlink r:id="rId3"><w:r><w:t>second card</w:t></w:r></w:hyperlink></w:p></w:tc></w:tr></w:tbl>
+<w:p><w:pPr><w:sectPr><w:headerReference w:type="default" r:id="rId4"/></w:sectPr></w:pPr></w:p>
+<w:p><w:pPr><w:pStyle w:val="Heading2"/></w:pPr><w:r><w:t>Next hat</w:t></w:r></w:p>
+<w:sectPr><w:pgSz w:w="12240" w:h="15840"/></w:sectPr>"#;
+
+fn source() -> Vec<u8> {
+    DocxBuilder::new()
+        .styles(
+            &[
+                heading_style("Heading1", 0, "<w:b/>"),
+                heading_style("Heading2", 1, "<w:b/>"),
+                heading_style("Heading4", 3, "<w:b/>"),
+            ]
+            .concat(),
+        )
+        .body(BODY)
+        .part("[Content_Types].xml", CONTENT_TYPES.as_bytes())
+        .part("_rels/.rels", PACKAGE_RELS.as_bytes())
+        .part("word/_rels/document.xml.rels", DOCUMENT_RELS.as_bytes())
+        .part("word/media/image1.png", b"\x89PNG")
+        .part("word/header1.xml", b"<w:hdr/>")
+        .part("word/numbering.xml", NUMBERING.as_bytes())
+        .build()
+}
+
+const NUMBERING: &str = r#"<w:numbering><w:abstractNum w:abstractNumId="0"><w:lvl w:ilvl="0"/></w:abstractNum><w:num w:numId="1"><w:abstractNumId w:val="0"/></w:num></w:numbering>"#;
+
+// another file, with its own namespaces, styles, list, image and footnote
+fn other_source() -> Vec<u8> {
+    let document = r#"<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main" xmlns:mc="http://schemas.openxmlformats.org/markup-compatibility/2006" xmlns:w14="http://schemas.microsoft.com/office/word/2010/wordml" mc:Ignorable="w14"><w:body>
+<w:p><w:pPr><w:pStyle w:val="Heading4"/></w:pPr><w:r><w:t>Other tag</w:t></w:r></w:p>
+<w:p w14:paraId="1A2B3C4D"><w:pPr><w:pStyle w:val="Cite"/><w:numPr><w:ilvl w:val="0"/><w:numId w:val="1"/></w:numPr></w:pPr><w:r><w:t>other card</w:t></w:r><w:r><w:footnoteReference w:id="1"/></w:r><w:r><w:drawing><a:blip r:embed="rId2"/></w:drawing></w:r></w:p>
+<w:sectPr><w:pgSz w:w="11906" w:h="16838"/></w:sectPr>
+</w:body></w:document>"#;
+    DocxBuilder::new()
+        .styles(
+            &[
+                heading_style("Heading4", 3, "<w:u/>"),
+                style("Cite", None, "<w:b/>"),
+            ]
+            .concat(),
+        )
+        .without_document()
+        .part("word/document.xml", document.as_bytes())
+        .part("[Content_Types].xml", CONTENT_TYPES.as_bytes())
+        .part("_rels/.rels", PACKAGE_RELS.as_bytes())
+        .part("word/_rels/document.xml.rels", DOCUMENT_RELS.as_bytes())
+        .part("word/media/image1.png", b"\x89PNG other")
+        .part("word/numbering.xml", NUMBERING.as_bytes())
+        .build()
+}
+
+fn copy_sections(links: &[usize]) -> Vec<u8> {
+    let source = source();
+    let mut doc = Document::new();
+    doc.load_bytes(&source).unwrap();
+    doc.export("speech")
+        .sections_to_docx(&source, links)
+        .unwrap()
+}
+
+fn read_part(docx: &[u8], name: &str) -> Option<String> {
+    let mut archive = ZipArchive::new(Cursor::new(docx)).unwrap();
+    let mut part = archive.by_name(name).ok()?;
+    let mut contents = Vec::new();
+    part.read_to_end(&mut contents).unwrap();
+    Some(String::from_utf8_lossy(&contents).into_owned())
+}
+
+fn texts(docx: &[u8]) -> Vec<String> {
+    let mut doc = Document::new();
+    doc.load_bytes(docx).unwrap();
+    doc.paras.iter().map(|para| para.text()).collect()
+}
+
+#[test]
+fn copies_sections_in_order() {
+    let docx = copy_sections(&[4, 2]);
+    assert_eq!(
+        texts(&docx),
+        vec!["Second tag", "second card", "", "First tag", "first card"]
+    );
+    // the page setup always comes along
+    let document = read_part(&docx, "word/document.xml").unwrap();
+    assert!(document.ends_with(
+        r#"<w:sectPr><w:pgSz w:w="12240" w:h="15840"/></w:sectPr></w:body></w:document>"#
+    ));
+}
+
+#[test]
+fn overlapping_sections_are_copied_once() {
+    let docx = copy_sections(&[1, 2, 4]);
+    assert_eq!(
+        texts(&docx),
+        vec![
+            "Hat",
+            "First tag",
+            "first card",
+            "Second tag",
+            "second card",
+            ""
+        ]
+    );
+}
+
+#[test]
+fn paragraphs_keep_their_xml() {
+    let docx = copy_sections(&[2]);
+    let document = read_part(&docx, "word/document.xml").unwrap();
+    assert!(document.contains(
+        r#"<w:p><w:r><w:rPr><w:b/></w:rPr><w:t>first card</w:t></w:r><w:r><w:drawing><a:blip r:embed="rId2"/></w:drawing></w:r></w:p>"#
+    ));
+    // the namespaces on the root are kept
+    assert!(document.starts_with("<w:document xmlns:w="));
+    assert_eq!(
+        read_part(&docx, "word/styles.xml"),
+        read_part(&source(), "word/styles.xml")
+    );
+}
+
+#[test]
+fn parts_come_along_if_a_copied_paragraph_uses_them() {
+    let docx = copy_sections(&[2]);
+    assert!(read_part(&docx, "word/media/image1.png").is_some());
+    assert!(read_part(&docx, "word/header1.xml").is_none());
+    let rels = read_part(&docx, "word/_rels/document.xml.rels").unwrap();
+    assert!(rels.contains(r#"Id="rId1""#));
+    assert!(rels.contains(r#"Id="rId2""#));
+    assert!(!rels.contains(r#"Id="rId3""#));
+    assert!(!rels.contains(r#"Id="rId4""#));
+    let content_types = read_part(&docx, "[Content_Types].xml").unwrap();
+    assert!(content_types.contains(r#"<Default Extension="png" ContentType="image/png"/>"#));
+    assert!(!content_types.contains("/word/header1.xml"));
+
+    let docx = copy_sections(&[4]);
+    assert!(read_part(&docx, "word/media/image1.png").is_none());
+    assert!(read_part(&docx, "word/header1.xml").is_some());
+    let rels = read_part(&docx, "word/_rels/document.xml.rels").unwrap();
+    assert!(rels.contains(
+        r#"<Relationship Id="rId3" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/hyperlink" Target="https://example.com/?a=1&amp;b=2" TargetMode="External"/>"#
+    ));
+    assert!(read_part(&docx, "[Content_Types].xml")
+        .unwrap()
+        .contains("/word/header1.xml"));
+}
+
+#[test]
+fn only_docx_files_can_be_copied_from() {
+    let source = source();
+    let mut doc = Document::new();
+    doc.load_bytes(&source).unwrap();
+    let export = doc.export("speech");
+    assert!(matches!(
+        export.sections_to_docx(b"{\\rtf1 Pocket\\par}", &[0]),
+        Err(WriteError::NotDocx)
+    ));
+    assert!(matches!(
+        export.sections_to_docx(&source, &[20]),
+        Err(WriteError::MissingParagraph(20))
+    ));
+}
+
+#[test]
+fn paragraphs_from_other_files_are_merged_in() {
+    let (base, other) = (source(), other_source());
+    let mut writer = DocxWriter::new(&base).unwrap();
+    let number = writer.add_source(&other).unwrap();
+    writer.copy(0, 2..=3).unwrap();
+    writer.copy(number, 0..=1).unwrap();
+    let docx = writer.finish().unwrap();
+    assert_eq!(
+        texts(&docx),
+        vec!["First tag", "first card", "Other tag", "other card"]
+    );
+
+    let document = read_part(&docx, "word/document.xml").unwrap();
+    // the base's page setup, with the other file's namespaces added
+    assert!(
+        document.contains(r#"xmlns:w14="http://schemas.microsoft.com/office/word/2010/wordml""#)
+    );
+    assert!(document.contains(r#"mc:Ignorable="w14""#));
+    assert!(document.contains(r#"<w:pgSz w:w="12240" w:h="15840"/>"#));
+    // lists go after the base's, and the base's footnotes aren't pointed to
+    assert!(document.contains(r#"<w:numId w:val="2"/>"#));
+    assert!(!document.contains("w:footnoteReference"));
+
+    // the images have the same name, so the other file's is renamed
+    assert!(document.contains(r#"<a:blip r:embed="s1rId2"/>"#));
+    let rels = read_part(&docx, "word/_rels/document.xml.rels").unwrap();
+    assert!(rels.contains(r#"Id="s1rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/image" Target="/word/media/image1-1.png""#));
+    assert_eq!(
+        read_part(&docx, "word/media/image1-1.png").unwrap(),
+        "\u{fffd}PNG other"
+    );
+    assert!(read_part(&docx, "[Content_Types].xml")
+        .unwrap()
+        .contains(r#"<Override PartName="/word/media/image1-1.png" ContentType="image/png"/>"#));
+
+    // styles the base has stay the same, others are added
+    let styles = read_part(&docx, "word/styles.xml").unwrap();
+    assert_eq!(styles.matches(r#"w:styleId="Heading4""#).count(), 1);
+    assert!(styles.contains(r#"w:styleId="Cite""#));
+    assert!(!styles.contains("<w:u/>"));
+    assert_eq!(
+        read_part(&docx, "word/numbering.xml").unwrap(),
+        r#"<w:numbering><w:abstractNum w:abstractNumId="0"><w:lvl w:ilvl="0"/></w:abstractNum><w:abstractNum w:abstractNumId="1"><w:lvl w:ilvl="0"/></w:abstractNum><w:num w:numId="1"><w:abstractNumId w:val="0"/></w:num><w:num w:numId="2"><w:abstractNumId w:val="1"/></w:num></w:numbering>"#
+    );
+}
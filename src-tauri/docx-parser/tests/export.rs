@@ -0,0 +1,526 @@
+mod common;
+
+use common::{heading_style, DocxBuilder};
+use docx_parser::{Document, Highlight, PageSetup, PaperSize, JSON_VERSION};
+
+use std::io::{Cursor, Read};
+
+use flate2::read::ZlibDecoder;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde_json::Value;
+use zip::{CompressionMethod, ZipArchive};
+
+// pocket, hat, two tags with a card each, then another hat
+fn debate_file() -> Document {
+    DocxBuilder::new()
+        .styles(
+            &[
+                heading_style("Heading1", 0, "<w:b/>"),
+                heading_style("Heading2", 1, "<w:b/>"),
+                heading_style("Heading4", 3, "<w:b/>"),
+            ]
+            .concat(),
+        )
+        .body(
+            r#"<w:p><w:pPr><w:pStyle w:val="Heading1"/></w:pPr><w:r><w:t>Pocket</w:t></w:r></w:p>
+<w:p><w:pPr><w:pStyle w:val="Heading2"/></w:pPr><w:r><w:t>Hat</w:t></w:r></w:p>
+<w:p><w:pPr><w:pStyle w:val="Heading4"/></w:pPr><w:r><w:t>First tag</w:t></w:r></w:p>
+<w:p><w:r><w:t>first card</w:t></w:r></w:p>
+<w:p><w:pPr><w:pStyle w:val="Heading4"/></w:pPr><w:r><w:t>Second tag</w:t></w:r></w:p>
+<w:p><w:r><w:t>second card</w:t></w:r></w:p>
+<w:p><w:pPr><w:pStyle w:val="Heading2"/></w:pPr><w:r><w:t>Next hat</w:t></w:r></w:p>"#,
+        )
+        .load()
+}
+
+fn indexes(export: &docx_parser::Export) -> Vec<usize> {
+    export.paras.iter().map(|para| para.index).collect()
+}
+
+#[test]
+fn section_ends_at_same_or_higher_level() {
+    let doc = debate_file();
+    let export = doc.export("speech");
+    let hat = export.section(1).unwrap();
+    assert_eq!(hat.title, "Hat");
+    assert_eq!(indexes(&hat), vec![1, 2, 3, 4, 5]);
+    let links: Vec<usize> = hat.outline_paras.iter().map(|para| para.link).collect();
+    assert_eq!(links, vec![1, 2, 4]);
+
+    let tag = export.section(2).unwrap();
+    assert_eq!(indexes(&tag), vec![2, 3]);
+    // the last section runs to the end of the document
+    assert_eq!(indexes(&export.section(6).unwrap()), vec![6]);
+    assert_eq!(indexes(&export.section(0).unwrap()).len(), 7);
+}
+
+#[test]
+fn section_of_body_text_is_one_paragraph() {
+    let doc = debate_file();
+    let card = doc.export("speech").section(3).unwrap();
+    assert_eq!(indexes(&card), vec![3]);
+    assert!(card.outline_paras.is_empty());
+    assert!(doc.export("speech").section(7).is_none());
+}
+
+#[test]
+fn title_comes_from_properties_first() {
+    let mut doc = debate_file();
+    assert_eq!(doc.export("speech").title, "speech");
+    doc.info.title = Some("  ".to_string());
+    assert_eq!(doc.export("speech").title, "speech");
+    doc.info.title = Some("Aff".to_string());
+    assert_eq!(doc.export("speech").title, "Aff");
+    assert_eq!(doc.info.title_or("speech"), "Aff");
+}
+
+#[test]
+fn sections_can_be_narrowed_again() {
+    let doc = debate_file();
+    let hat = doc.export("speech").section(1).unwrap();
+    // positions are in the narrowed paragraphs, not the document
+    assert_eq!(indexes(&hat.section(3).unwrap()), vec![4, 5]);
+}
+
+#[test]
+fn html_has_headings_and_contents() {
+    let html = debate_file().export("speech").to_html();
+    assert!(html.contains("<title>speech</title>"));
+    assert!(html.contains(r#"<h1 id="p0"><b>Pocket</b></h1>"#));
+    assert!(html.contains(r#"<h4 id="p2"><b>First tag</b></h4>"#));
+    assert!(html.contains("<p>first card</p>"));
+    assert!(html.contains(
+        "<nav>\n<ul>\n<li><a href=\"#p0\">Pocket</a><ul>\n<li><a href=\"#p1\">Hat</a><ul>\n\
+         <li><a href=\"#p2\">First tag</a></li>\n<li><a href=\"#p4\">Second tag</a></li></ul>\n\
+         </li>\n<li><a href=\"#p6\">Next hat</a></li></ul>\n</li></ul>\n</nav>"
+    ));
+}
+
+#[test]
+fn html_formats_runs() {
+    let doc = DocxBuilder::new()
+        .body(
+            r#"<w:p><w:r><w:rPr><w:u w:val="single"/><w:highlight w:val="cyan"/><w:sz w:val="22"/></w:rPr><w:t>read</w:t></w:r><w:r><w:t> skipped</w:t></w:r></w:p><w:p></w:p>"#,
+        )
+        .load();
+    let html = doc.export("card").to_html();
+    assert!(html.contains(
+        r#"<p><span style="font-size: 11pt"><u><mark>read</mark></u></span> skipped</p>"#
+    ));
+    assert!(html.contains("<p><br></p>"));
+    // no headings, no contents
+    assert!(!html.contains("<nav>"));
+}
+
+#[test]
+fn html_escapes_text() {
+    let doc = DocxBuilder::new()
+        .body("<w:p><w:r><w:t>&lt;script&gt; &amp; \"quotes\"</w:t></w:r></w:p>")
+        .load();
+    let html = doc.export("<title>").to_html();
+    assert!(html.contains("<p>&lt;script&gt; &amp; &quot;quotes&quot;</p>"));
+    assert!(html.contains("<title>&lt;title&gt;</title>"));
+}
+
+#[test]
+fn markdown_has_headings_and_paragraphs() {
+    let markdown = debate_file().export("speech").to_markdown(Highlight::Html);
+    assert_eq!(
+        markdown,
+        "# Pocket\n\n## Hat\n\n#### First tag\n\nfirst card\n\n#### Second tag\n\nsecond card\n\n## Next hat\n"
+    );
+}
+
+#[test]
+fn markdown_formats_runs() {
+    let doc = DocxBuilder::new()
+        .body(
+            r#"<w:p><w:r><w:t xml:space="preserve">plain </w:t></w:r><w:r><w:rPr><w:b/></w:rPr><w:t xml:space="preserve">bold </w:t></w:r><w:r><w:rPr><w:b/><w:u w:val="single"/></w:rPr><w:t>both</w:t></w:r><w:r><w:rPr><w:u w:val="single"/><w:highlight w:val="cyan"/></w:rPr><w:t xml:space="preserve"> read</w:t></w:r></w:p><w:p></w:p>"#,
+        )
+        .load();
+    let export = doc.export("card");
+    // spaces stay outside the markers, or they aren't read as formatting
+    assert_eq!(
+        export.to_markdown(Highlight::Html),
+        "plain **bold <u>both</u>** <u><mark>read</mark></u>\n"
+    );
+    assert_eq!(
+        export.to_markdown(Highlight::Mark),
+        "plain **bold <u>both</u>** <u>==read==</u>\n"
+    );
+}
+
+#[test]
+fn markdown_escapes_text() {
+    let doc = DocxBuilder::new()
+        .body(
+            "<w:p><w:r><w:t>*not* _emphasis_ [link](url) &lt;b&gt; a==b</w:t></w:r></w:p>\
+             <w:p><w:r><w:t>1. not a list</w:t></w:r></w:p>\
+             <w:p><w:r><w:t># not a heading</w:t></w:r></w:p>",
+        )
+        .load();
+    let export = doc.export("card");
+    assert_eq!(
+        export.to_markdown(Highlight::Html),
+        "\\*not\\* \\_emphasis\\_ \\[link\\](url) \\<b\\> a==b\n\n1\\. not a list\n\n\\# not a heading\n"
+    );
+    assert!(export.to_markdown(Highlight::Mark).contains("a\\=\\=b"));
+}
+
+fn pdf_pages(pdf: &[u8]) -> usize {
+    String::from_utf8_lossy(pdf)
+        .matches("/Type /Page\n")
+        .count()
+}
+
+// the text drawn on every page, from the compressed content streams
+fn pdf_text(pdf: &[u8]) -> String {
+    let mut text = Vec::new();
+    let mut rest = pdf;
+    while let Some(start) = find(rest, b"stream\n") {
+        rest = &rest[start + 7..];
+        let end = find(rest, b"\nendstream").unwrap();
+        let mut content = Vec::new();
+        ZlibDecoder::new(&rest[..end])
+            .read_to_end(&mut content)
+            .unwrap();
+        text.extend(content);
+        rest = &rest[end + b"\nendstream".len()..];
+    }
+    String::from_utf8_lossy(&text).into_owned()
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[test]
+fn pdf_has_bookmarks_for_headings() {
+    let pdf = debate_file().export("speech").to_pdf(&PageSetup::LETTER);
+    let pdf_string = String::from_utf8_lossy(&pdf);
+    assert!(pdf.starts_with(b"%PDF-"));
+    assert!(pdf_string.contains("/PageMode /UseOutlines"));
+    for title in ["Pocket", "Hat", "First tag", "Second tag", "Next hat"] {
+        assert!(
+            pdf_string.contains(&format!("/Title ({})", title)),
+            "no bookmark for {}",
+            title
+        );
+    }
+    assert_eq!(pdf_pages(&pdf), 1);
+}
+
+#[test]
+fn pdf_draws_formatting() {
+    let doc = DocxBuilder::new()
+        .body(
+            r#"<w:p><w:r><w:rPr><w:b/><w:sz w:val="26"/></w:rPr><w:t>Smith 22</w:t></w:r><w:r><w:rPr><w:u w:val="single"/><w:highlight w:val="cyan"/></w:rPr><w:t xml:space="preserve"> read this</w:t></w:r></w:p>"#,
+        )
+        .load();
+    let text = pdf_text(&doc.export("card").to_pdf(&PageSetup::LETTER));
+    // bold is the second font, sizes are in points
+    assert!(text.contains("/F2 13 Tf"));
+    assert!(text.contains("(Smith 22) Tj"));
+    assert!(text.contains("( read this) Tj"));
+    // the highlight's yellow and the underline's rectangle
+    assert!(text.contains("1 1 0 rg"));
+    assert_eq!(text.matches(" re\nf").count(), 2);
+}
+
+#[test]
+fn pdf_wraps_lines_and_pages() {
+    let sentence = "the quick brown fox jumps over the lazy dog ".repeat(20);
+    let body = format!("<w:p><w:r><w:t>{}</w:t></w:r></w:p>", sentence).repeat(40);
+    let doc = DocxBuilder::new().body(&body).load();
+    let export = doc.export("long");
+    let letter = export.to_pdf(&PageSetup::LETTER);
+    assert!(pdf_pages(&letter) > 1);
+    // every word is drawn, none are cut off at the margin
+    let text = pdf_text(&letter);
+    assert_eq!(text.matches("fox").count(), 800);
+    // less room per page means more pages
+    let small = PageSetup {
+        margin_top: 200.0,
+        margin_bottom: 200.0,
+        ..PageSetup::LETTER
+    };
+    assert!(pdf_pages(&export.to_pdf(&small)) > pdf_pages(&letter));
+}
+
+#[test]
+fn paper_sizes_are_named_like_the_app_sends_them() {
+    let paper: PaperSize = serde_json::from_str(r#""a4""#).unwrap();
+    assert_eq!(paper.page_setup(), PageSetup::A4);
+    let paper: PaperSize = serde_json::from_str(r#""letter""#).unwrap();
+    assert_eq!(paper.page_setup(), PageSetup::LETTER);
+}
+
+#[test]
+fn margins_must_leave_room_for_text() {
+    let page = PageSetup::A4.with_margin(36.0).unwrap();
+    assert_eq!(page.width, PageSetup::A4.width);
+    assert_eq!(
+        (
+            page.margin_top,
+            page.margin_right,
+            page.margin_bottom,
+            page.margin_left
+        ),
+        (36.0, 36.0, 36.0, 36.0)
+    );
+    assert_eq!(PageSetup::LETTER.with_margin(0.0).unwrap().margin_left, 0.0);
+    assert!(PageSetup::LETTER.with_margin(306.0).is_none());
+    assert!(PageSetup::LETTER.with_margin(-1.0).is_none());
+    assert!(PageSetup::LETTER.with_margin(f32::NAN).is_none());
+}
+
+#[test]
+fn pdf_replaces_characters_without_glyphs() {
+    let doc = DocxBuilder::new()
+        .body("<w:p><w:r><w:t>caf\u{e9} \u{201c}quoted\u{201d} \u{6f22}</w:t></w:r></w:p>")
+        .load();
+    let pdf = doc.export("card").to_pdf(&PageSetup::A4);
+    // Windows-1252 has é and curly quotes, but not Chinese
+    let content = pdf_text(&pdf);
+    assert!(content.contains("<636166E920"));
+    assert!(content.contains("3F>"));
+}
+
+#[test]
+fn clipboard_slices_runs_at_offsets() {
+    let doc = debate_file();
+    let export = doc.export("speech");
+    // from "tag" in the first tag to "second" in the second card
+    let clipboard = export.clipboard((2, 6), (5, 6)).unwrap();
+    assert_eq!(clipboard.text, "tag\nfirst card\nSecond tag\nsecond");
+    assert!(clipboard.html.contains(r#"<h4 id="p2"><b>tag</b></h4>"#));
+    assert!(clipboard.html.contains("<p>second</p>"));
+    // offsets past the end are the end of the paragraph
+    assert_eq!(
+        export.clipboard((3, 0), (3, 100)).unwrap().text,
+        "first card"
+    );
+    assert!(export.clipboard((3, 0), (2, 0)).is_none());
+    assert!(export.clipboard((3, 0), (9, 0)).is_none());
+}
+
+#[test]
+fn clipboard_rtf_keeps_formatting() {
+    let doc = DocxBuilder::new()
+        .styles(&heading_style("Heading4", 3, "<w:b/>"))
+        .body(
+            r#"<w:p><w:pPr><w:pStyle w:val="Heading4"/></w:pPr><w:r><w:t>Smith {22}</w:t></w:r></w:p>
+<w:p><w:r><w:rPr><w:u w:val="single"/><w:highlight w:val="cyan"/><w:sz w:val="22"/></w:rPr><w:t>read caf&#233; &#128512;</w:t></w:r><w:r><w:t xml:space="preserve"> skipped \</w:t></w:r></w:p>"#,
+        )
+        .load();
+    let rtf = doc.export("card").clipboard((0, 0), (1, 100)).unwrap().rtf;
+    assert!(rtf.starts_with("{\\rtf1"));
+    assert!(rtf.contains("Smith \\{22\\}"));
+    assert!(rtf.contains("caf\\u233?"));
+
+    // the rtf reads back the same
+    let mut pasted = Document::new();
+    pasted.load_bytes(rtf.as_bytes()).unwrap();
+    assert_eq!(pasted.paras.len(), 2);
+    assert_eq!(pasted.paras[0].outline_level, Some(3));
+    assert_eq!(pasted.paras[0].text(), "Smith {22}");
+    assert_eq!(pasted.paras[0].runs[0].style.bold, Some(true));
+    let read = &pasted.paras[1].runs[0];
+    assert_eq!(read.text, "read caf\u{e9} \u{1f600}");
+    assert_eq!(read.style.underline, Some(true));
+    assert_eq!(read.style.highlight, Some(true));
+    assert_eq!(read.style.size, Some(22));
+    assert_eq!(pasted.paras[1].runs[1].text, " skipped \\");
+    assert_ne!(pasted.paras[1].runs[1].style.underline, Some(true));
+}
+
+#[test]
+fn clipboard_windows_html_offsets_point_at_the_fragment() {
+    let doc = debate_file();
+    let clipboard = doc.export("speech").clipboard((2, 0), (3, 100)).unwrap();
+    let html = clipboard.windows_html();
+    let offset = |name: &str| -> usize {
+        let start = html.find(name).unwrap() + name.len() + 1;
+        html[start..start + 10].parse().unwrap()
+    };
+    assert!(html.starts_with("Version:0.9\r\n"));
+    assert!(html[offset("StartHTML")..].starts_with("<html>"));
+    assert_eq!(offset("EndHTML"), html.len());
+    assert_eq!(
+        &html[offset("StartFragment")..offset("EndFragment")],
+        clipboard.html
+    );
+}
+
+#[test]
+fn json_has_resolved_styles_and_outline() {
+    let doc = debate_file();
+    let json: Value = serde_json::from_str(&doc.export("speech").to_json(&doc.info)).unwrap();
+    assert_eq!(json["version"], JSON_VERSION);
+    assert_eq!(json["title"], "speech");
+    assert_eq!(json["paras"].as_array().unwrap().len(), 7);
+    assert_eq!(
+        json["paras"][2],
+        serde_json::json!({
+            "index": 2,
+            "outline_level": 3,
+            "runs": [{"text": "First tag", "bold": true, "underline": false, "highlight": false, "size": null}],
+        })
+    );
+    assert_eq!(json["paras"][3]["outline_level"], Value::Null);
+    assert_eq!(
+        json["outline"][2],
+        serde_json::json!({"index": 2, "para": 2, "outline_level": 3, "text": "First tag"})
+    );
+    // a section keeps the document's indexes
+    let hat = doc.export("speech").section(1).unwrap();
+    let json: Value = serde_json::from_str(&hat.to_json(&doc.info)).unwrap();
+    assert_eq!(json["title"], "Hat");
+    assert_eq!(json["paras"][0]["index"], 1);
+    assert_eq!(json["outline"][1]["para"], 2);
+}
+
+// every object has the fields the schema says it must, and no others
+fn check_fields(schema: &Value, definition: &Value, value: &Value) {
+    let definition = match definition["$ref"].as_str() {
+        Some(reference) => &schema["$defs"][reference.trim_start_matches("#/$defs/")],
+        None => definition,
+    };
+    if let Some(object) = value.as_object() {
+        let mut required: Vec<&str> = definition["required"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|key| key.as_str().unwrap())
+            .collect();
+        let mut keys: Vec<&str> = object.keys().map(|key| key.as_str()).collect();
+        required.sort_unstable();
+        keys.sort_unstable();
+        assert_eq!(keys, required);
+        for (key, value) in object {
+            check_fields(schema, &definition["properties"][key], value);
+        }
+    } else if let Some(array) = value.as_array() {
+        for item in array {
+            check_fields(schema, &definition["items"], item);
+        }
+    }
+}
+
+#[test]
+fn json_matches_schema() {
+    let schema: Value =
+        serde_json::from_str(include_str!("../schema/document.schema.json")).unwrap();
+    assert_eq!(schema["properties"]["version"]["const"], JSON_VERSION);
+    let doc = DocxBuilder::new()
+        .body(r#"<w:p><w:r><w:rPr><w:u w:val="single"/><w:sz w:val="22"/></w:rPr><w:t>card</w:t></w:r></w:p>"#)
+        .load();
+    let json: Value = serde_json::from_str(&doc.export("card").to_json(&doc.info)).unwrap();
+    check_fields(&schema, &schema, &json);
+    assert_eq!(json["paras"][0]["runs"][0]["size"], 22);
+    assert_eq!(json["paras"][0]["runs"][0]["underline"], true);
+}
+
+// every file in the epub, in order, after checking the xml is well formed
+fn epub_files(epub: &[u8]) -> Vec<(String, String)> {
+    let mut archive = ZipArchive::new(Cursor::new(epub)).unwrap();
+    let mut files = Vec::new();
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i).unwrap();
+        let mut contents = String::new();
+        file.read_to_string(&mut contents).unwrap();
+        if !file.name().ends_with(".css") && file.name() != "mimetype" {
+            let mut reader = Reader::from_str(&contents);
+            let mut buf = Vec::new();
+            loop {
+                match reader.read_event(&mut buf) {
+                    Ok(Event::Eof) => break,
+                    Err(e) => panic!("{} isn't well formed: {}", file.name(), e),
+                    _ => (),
+                }
+            }
+        }
+        files.push((file.name().to_string(), contents));
+    }
+    files
+}
+
+fn epub_file<'a>(files: &'a [(String, String)], name: &str) -> &'a str {
+    &files.iter().find(|(file, _)| file == name).unwrap().1
+}
+
+#[test]
+fn epub_splits_chapters_at_level() {
+    let doc = debate_file();
+    let epub = doc.export("speech").to_epub(&doc.info, 1);
+    // readers recognize the file by an uncompressed mimetype at the start
+    let mut archive = ZipArchive::new(Cursor::new(&epub)).unwrap();
+    let mimetype = archive.by_index(0).unwrap();
+    assert_eq!(mimetype.name(), "mimetype");
+    assert_eq!(mimetype.compression(), CompressionMethod::Stored);
+    drop(mimetype);
+
+    let files = epub_files(&epub);
+    let chapters: Vec<&str> = files
+        .iter()
+        .filter(|(name, _)| name.starts_with("OEBPS/chapter"))
+        .map(|(_, contents)| contents.as_str())
+        .collect();
+    // the pocket, then a chapter for each hat
+    assert_eq!(chapters.len(), 3);
+    assert!(chapters[0].contains("<title>Pocket</title>"));
+    assert!(!chapters[0].contains("Hat"));
+    assert!(chapters[1].contains(r#"<h4 id="p2"><b>First tag</b></h4>"#));
+    assert!(chapters[1].contains("<p>second card</p>"));
+    assert!(chapters[2].contains("<title>Next hat</title>"));
+    // splitting at the top level keeps everything together
+    let epub = doc.export("speech").to_epub(&doc.info, 0);
+    assert!(epub_files(&epub)
+        .iter()
+        .all(|(name, _)| name != "OEBPS/chapter2.xhtml"));
+
+    let opf = epub_file(&files, "OEBPS/content.opf");
+    assert!(opf.contains("<dc:title>speech</dc:title>"));
+    assert!(opf.contains(r#"<itemref idref="chapter3"/>"#));
+    assert!(opf.contains(r#"properties="nav""#));
+}
+
+#[test]
+fn epub_contents_nest_headings() {
+    let doc = debate_file();
+    let files = epub_files(&doc.export("speech").to_epub(&doc.info, 1));
+    let nav = epub_file(&files, "OEBPS/nav.xhtml");
+    assert!(nav.contains(
+        "<ol>\n<li><a href=\"chapter1.xhtml#p0\">Pocket</a>\n<ol>\n\
+         <li><a href=\"chapter2.xhtml#p1\">Hat</a>\n<ol>\n\
+         <li><a href=\"chapter2.xhtml#p2\">First tag</a></li>\n\
+         <li><a href=\"chapter2.xhtml#p4\">Second tag</a></li>\n</ol>\n</li>\n\
+         <li><a href=\"chapter3.xhtml#p6\">Next hat</a></li>\n</ol>\n</li>\n</ol>"
+    ));
+    let ncx = epub_file(&files, "OEBPS/toc.ncx");
+    assert_eq!(ncx.matches("<navPoint ").count(), 5);
+    assert!(ncx.contains(
+        r#"<navPoint id="nav3" playOrder="4"><navLabel><text>Second tag</text></navLabel><content src="chapter2.xhtml#p4"/>"#
+    ));
+}
+
+#[test]
+fn epub_keeps_formatting_as_classes() {
+    let doc = DocxBuilder::new()
+        .body(
+            r#"<w:p><w:r><w:rPr><w:u w:val="single"/><w:highlight w:val="cyan"/><w:sz w:val="36"/></w:rPr><w:t>read</w:t></w:r><w:r><w:rPr><w:b/></w:rPr><w:t xml:space="preserve"> &amp; more</w:t></w:r></w:p><w:p></w:p>"#,
+        )
+        .load();
+    let files = epub_files(&doc.export("card").to_epub(&doc.info, 1));
+    let chapter = epub_file(&files, "OEBPS/chapter1.xhtml");
+    assert!(chapter.contains(
+        r#"<p><span class="underline highlight" style="font-size: 1.5em">read</span><b> &amp; more</b></p>"#
+    ));
+    assert!(chapter.contains("<p><br/></p>"));
+    assert!(epub_file(&files, "OEBPS/style.css").contains(".highlight"));
+    // without headings, the contents still link to the text
+    assert!(epub_file(&files, "OEBPS/nav.xhtml").contains(r#"<a href="chapter1.xhtml">card</a>"#));
+}
@@ -0,0 +1,101 @@
+mod common;
+
+use common::{outline_levels, plain, seed, texts};
+use docx_parser::{Document, Style};
+
+fn load(xml: &str) -> Document {
+    let mut doc = Document::new();
+    doc.load_bytes(xml.as_bytes()).unwrap();
+    doc
+}
+
+// a flat opc package holding each part as inline xml
+fn flat_opc(parts: &[(&str, &str)]) -> String {
+    let mut xml = String::from(
+        r#"<?xml version="1.0" standalone="yes"?><?mso-application progid="Word.Document"?><pkg:package xmlns:pkg="http://schemas.microsoft.com/office/2006/xmlPackage">"#,
+    );
+    for (name, contents) in parts {
+        xml.push_str(&format!(
+            r#"<pkg:part pkg:name="{}"><pkg:xmlData>{}</pkg:xmlData></pkg:part>"#,
+            name, contents
+        ));
+    }
+    xml.push_str("</pkg:package>");
+    xml
+}
+
+#[test]
+fn reads_flat_opc_file() {
+    let mut doc = Document::new();
+    doc.load_bytes(&seed("flat_opc.xml")).unwrap();
+    let paras: Vec<Vec<&str>> = doc.paras.iter().map(|para| texts(&para.runs)).collect();
+    assert_eq!(paras, vec![vec!["Hello"], vec!["bold"]]);
+    assert_eq!(outline_levels(&doc), vec![Some(0), None]);
+    assert_eq!(
+        doc.paras[0].runs[0].style,
+        Style {
+            size: Some(32),
+            ..plain()
+        }
+    );
+    assert_eq!(doc.paras[1].runs[0].style.bold, Some(true));
+    assert!(doc.warnings.is_empty());
+}
+
+#[test]
+fn reads_word_2003_file() {
+    let mut doc = Document::new();
+    doc.load_bytes(&seed("word2003.xml")).unwrap();
+    // the picture's base64 data isn't text
+    assert_eq!(doc.paras.len(), 1);
+    assert_eq!(texts(&doc.paras[0].runs), vec!["Head"]);
+    assert_eq!(outline_levels(&doc), vec![Some(0)]);
+    assert_eq!(doc.paras[0].runs[0].style.underline, Some(true));
+    assert_eq!(doc.outline_paras.len(), 1);
+}
+
+#[test]
+fn reads_flat_opc_properties() {
+    let doc = load(&flat_opc(&[
+        (
+            "/docProps/core.xml",
+            r#"<cp:coreProperties xmlns:cp="cp" xmlns:dc="dc" xmlns:dcterms="dcterms"><dc:title>Aff Case</dc:title><dc:creator>Ann</dc:creator><cp:lastModifiedBy>Bo</cp:lastModifiedBy><dcterms:modified>2022-08-01T12:00:00Z</dcterms:modified></cp:coreProperties>"#,
+        ),
+        (
+            "/docProps/app.xml",
+            "<Properties><Pages>3</Pages><Application>Microsoft Office Word</Application></Properties>",
+        ),
+        (
+            "/word/document.xml",
+            r#"<w:document xmlns:w="w"><w:body><w:p><w:r><w:t>text</w:t></w:r></w:p></w:body></w:document>"#,
+        ),
+    ]));
+    assert_eq!(doc.info.title.as_deref(), Some("Aff Case"));
+    assert_eq!(doc.info.creator.as_deref(), Some("Ann"));
+    assert_eq!(doc.info.last_modified_by.as_deref(), Some("Bo"));
+    assert_eq!(doc.info.modified.as_deref(), Some("2022-08-01T12:00:00Z"));
+    assert_eq!(doc.info.pages, Some(3));
+    assert_eq!(
+        doc.info.application.as_deref(),
+        Some("Microsoft Office Word")
+    );
+    assert_eq!(texts(&doc.paras[0].runs), vec!["text"]);
+}
+
+#[test]
+fn missing_document_part_is_a_warning() {
+    let doc = load(&flat_opc(&[("/word/styles.xml", "<w:styles/>")]));
+    assert!(doc.paras.is_empty());
+    assert_eq!(doc.warnings.len(), 1);
+}
+
+#[test]
+fn other_xml_is_not_a_document() {
+    // a w:body somewhere inside doesn't make it word xml
+    let doc = load(
+        r#"<?xml version="1.0"?><html><body><w:body><w:p><w:r><w:t>hidden</w:t></w:r></w:p></w:body></body></html>"#,
+    );
+    assert!(doc.paras.is_empty());
+    assert_eq!(doc.warnings.len(), 1);
+    assert!(doc.warnings[0].contains("html"));
+}
@@ -0,0 +1,50 @@
+// inputs that crashed the fuzz targets in fuzz/, fed through the same calls. each
+// directory in tests/fuzz is named after the target that found its inputs.
+
+use docx_parser::Document;
+
+use std::fs;
+use std::path::Path;
+
+fn for_each_input(target: &str, run: fn(&[u8])) {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fuzz")
+        .join(target);
+    let mut count = 0;
+    for entry in fs::read_dir(&dir).unwrap() {
+        let path = entry.unwrap().path();
+        let bytes = fs::read(&path).unwrap();
+        // name the input that panicked, the panic message alone doesn't
+        let result = std::panic::catch_unwind(|| run(&bytes));
+        assert!(result.is_ok(), "{} panicked", path.display());
+        count += 1;
+    }
+    assert!(count > 0, "no inputs in {}", dir.display());
+}
+
+#[test]
+fn load_style_map() {
+    for_each_input("load_style_map", |bytes| {
+        let mut doc = Document::new();
+        doc.load_style_map(&String::from_utf8_lossy(bytes));
+    });
+}
+
+#[test]
+fn load_paras() {
+    for_each_input("load_paras", |bytes| {
+        let data = String::from_utf8_lossy(bytes);
+        let (styles, document) = data.split_once('\0').unwrap_or(("", &data));
+        let mut doc = Document::new();
+        doc.load_style_map(styles);
+        doc.load_paras(document);
+    });
+}
+
+#[test]
+fn load_bytes() {
+    for_each_input("load_bytes", |bytes| {
+        let mut doc = Document::new();
+        let _ = doc.load_bytes_with_password(bytes, Some("password"));
+    });
+}
@@ -0,0 +1,118 @@
+mod common;
+
+use common::{outline_levels, plain, seed, texts, OdtBuilder};
+use docx_parser::{Document, Style};
+
+fn heading_styles() -> String {
+    r#"<style:style style:name="Heading" style:family="paragraph"/>
+       <style:style style:name="Heading_20_2" style:family="paragraph" style:parent-style-name="Heading" style:default-outline-level="2"/>"#
+        .to_string()
+}
+
+#[test]
+fn reads_small_file() {
+    let mut doc = Document::new();
+    doc.load_bytes(&seed("small.odt")).unwrap();
+    let paras: Vec<Vec<&str>> = doc.paras.iter().map(|para| texts(&para.runs)).collect();
+    // the footnote inside the second paragraph is skipped
+    assert_eq!(
+        paras,
+        vec![
+            vec!["Tag"],
+            vec!["Plain ", "cite  here", " end"],
+            vec!["styled heading"],
+        ]
+    );
+    assert_eq!(outline_levels(&doc), vec![Some(1), None, Some(0)]);
+    assert_eq!(
+        doc.paras[1].runs[1].style,
+        Style {
+            underline: Some(true),
+            highlight: Some(true),
+            // from the default paragraph style
+            size: Some(22),
+            ..plain()
+        }
+    );
+    assert_eq!(doc.outline_paras.len(), 2);
+    assert!(doc.warnings.is_empty());
+}
+
+#[test]
+fn headings_take_their_own_level_first() {
+    let doc = OdtBuilder::new()
+        .styles(&heading_styles())
+        .body(
+            r#"<text:h text:style-name="Heading_20_2" text:outline-level="4">own</text:h>
+               <text:h text:style-name="Heading_20_2">style</text:h>
+               <text:h>neither</text:h>
+               <text:p text:style-name="Heading_20_2">paragraph with style</text:p>
+               <text:p>body</text:p>"#,
+        )
+        .load();
+    assert_eq!(
+        outline_levels(&doc),
+        vec![Some(3), Some(1), Some(0), Some(1), None]
+    );
+}
+
+#[test]
+fn levels_past_word_are_clamped() {
+    let doc = OdtBuilder::new()
+        .body(
+            r#"<text:h text:outline-level="9">nine</text:h>
+               <text:h text:outline-level="10">ten</text:h>
+               <text:h text:outline-level="11">too deep</text:h>
+               <text:h text:outline-level="0">too shallow</text:h>"#,
+        )
+        .load();
+    // levels outside odf's 1 to 10 are ignored, like a missing level
+    assert_eq!(
+        outline_levels(&doc),
+        vec![Some(8), Some(8), Some(0), Some(0)]
+    );
+}
+
+#[test]
+fn styles_inherit_from_parents() {
+    let doc = OdtBuilder::new()
+        .styles(
+            r#"<style:default-style style:family="paragraph"><style:text-properties fo:font-size="11pt"/></style:default-style>
+               <style:style style:name="Tag" style:family="paragraph" style:default-outline-level="3"><style:text-properties fo:font-weight="bold"/></style:style>"#,
+        )
+        .automatic_styles(
+            r##"<style:style style:name="P1" style:family="paragraph" style:parent-style-name="Tag"><style:text-properties style:text-underline-style="solid"/></style:style>
+               <style:style style:name="T1" style:family="text"><style:text-properties fo:font-weight="normal" fo:background-color="#00ffff"/></style:style>"##,
+        )
+        .body(r#"<text:p text:style-name="P1">tag <text:span text:style-name="T1">cite</text:span></text:p>"#)
+        .load();
+    assert_eq!(outline_levels(&doc), vec![Some(2)]);
+    let runs = &doc.paras[0].runs;
+    assert_eq!(texts(runs), vec!["tag ", "cite"]);
+    assert_eq!(
+        runs[0].style,
+        Style {
+            bold: Some(true),
+            underline: Some(true),
+            highlight: None,
+            size: Some(22),
+        }
+    );
+    assert_eq!(
+        runs[1].style,
+        Style {
+            bold: Some(false),
+            underline: Some(true),
+            highlight: Some(true),
+            size: Some(22),
+        }
+    );
+}
+
+#[test]
+fn spaces_are_expanded() {
+    let doc = OdtBuilder::new()
+        .body(r#"<text:p>a<text:s/>b<text:s text:c="3"/>c</text:p>"#)
+        .load();
+    assert_eq!(doc.paras[0].text(), "a b   c");
+}
@@ -0,0 +1,223 @@
+mod common;
+
+use common::{heading_style, plain, style, texts, DocxBuilder};
+use docx_parser::Style;
+
+#[test]
+fn reads_paragraphs_in_order() {
+    let doc = DocxBuilder::new()
+        .body(
+            "<w:p><w:r><w:t>first</w:t></w:r></w:p>\
+             <w:p></w:p>\
+             <w:p><w:r><w:t>third</w:t></w:r></w:p>",
+        )
+        .load();
+    assert_eq!(doc.paras.len(), 3);
+    let indexes: Vec<usize> = doc.paras.iter().map(|para| para.index).collect();
+    assert_eq!(indexes, vec![0, 1, 2]);
+    assert_eq!(texts(&doc.paras[0].runs), vec!["first"]);
+    assert!(doc.paras[1].runs.is_empty());
+    assert_eq!(texts(&doc.paras[2].runs), vec!["third"]);
+    assert!(doc.warnings.is_empty());
+}
+
+#[test]
+fn applies_direct_formatting() {
+    let doc = DocxBuilder::new()
+        .body(
+            r#"<w:p><w:r><w:rPr><w:b/><w:u w:val="single"/><w:highlight w:val="yellow"/><w:sz w:val="26"/></w:rPr><w:t>card</w:t></w:r></w:p>"#,
+        )
+        .load();
+    assert_eq!(
+        doc.paras[0].runs[0].style,
+        Style {
+            bold: Some(true),
+            underline: Some(true),
+            highlight: Some(true),
+            size: Some(26),
+        }
+    );
+}
+
+#[test]
+fn direct_formatting_can_turn_off_style() {
+    let doc = DocxBuilder::new()
+        .styles(&style("Tag", None, "<w:b/>"))
+        .body(
+            r#"<w:p><w:pPr><w:pStyle w:val="Tag"/></w:pPr><w:r><w:t>bold </w:t></w:r><w:r><w:rPr><w:b w:val="0"/></w:rPr><w:t>not bold</w:t></w:r></w:p>"#,
+        )
+        .load();
+    let runs = &doc.paras[0].runs;
+    assert_eq!(texts(runs), vec!["bold ", "not bold"]);
+    assert_eq!(runs[0].style.bold, Some(true));
+    assert_eq!(runs[1].style.bold, Some(false));
+}
+
+#[test]
+fn run_formatting_doesnt_leak_into_next_run() {
+    let doc = DocxBuilder::new()
+        .body(
+            "<w:p><w:r><w:rPr><w:b/></w:rPr><w:t>bold</w:t></w:r><w:r><w:t> plain</w:t></w:r></w:p>",
+        )
+        .load();
+    let runs = &doc.paras[0].runs;
+    assert_eq!(texts(runs), vec!["bold", " plain"]);
+    assert_eq!(runs[0].style.bold, Some(true));
+    assert_eq!(runs[1].style, plain());
+}
+
+#[test]
+fn uses_normal_style_by_default() {
+    let doc = DocxBuilder::new()
+        .styles(&style("Normal", None, r#"<w:sz w:val="22"/>"#))
+        .body("<w:p><w:r><w:t>one</w:t></w:r></w:p><w:p><w:r><w:t>two</w:t></w:r></w:p>")
+        .load();
+    for para in doc.paras.iter() {
+        assert_eq!(para.runs[0].style.size, Some(22));
+    }
+}
+
+#[test]
+fn paragraph_style_applies_to_runs() {
+    let doc = DocxBuilder::new()
+        .styles(
+            &[
+                style("Normal", None, r#"<w:sz w:val="22"/>"#),
+                style("Cite", Some("Normal"), "<w:b/>"),
+            ]
+            .concat(),
+        )
+        .body(
+            r#"<w:p><w:pPr><w:pStyle w:val="Cite"/></w:pPr><w:r><w:t>Smith 22</w:t></w:r></w:p><w:p><w:r><w:t>after</w:t></w:r></w:p>"#,
+        )
+        .load();
+    assert_eq!(doc.paras[0].runs[0].style.bold, Some(true));
+    assert_eq!(doc.paras[0].runs[0].style.size, Some(22));
+    // the paragraph style ends with its paragraph
+    assert_eq!(doc.paras[1].runs[0].style.bold, None);
+    assert_eq!(doc.paras[1].runs[0].style.size, Some(22));
+}
+
+#[test]
+fn run_style_applies_on_top_of_paragraph_style() {
+    let doc = DocxBuilder::new()
+        .styles(
+            &[
+                style("Big", None, r#"<w:sz w:val="32"/>"#),
+                style("Emphasis", None, r#"<w:u w:val="single"/>"#),
+            ]
+            .concat(),
+        )
+        .body(
+            r#"<w:p><w:pPr><w:pStyle w:val="Big"/></w:pPr><w:r><w:rPr><w:rStyle w:val="Emphasis"/></w:rPr><w:t>both</w:t></w:r></w:p>"#,
+        )
+        .load();
+    let style = &doc.paras[0].runs[0].style;
+    assert_eq!(style.size, Some(32));
+    assert_eq!(style.underline, Some(true));
+}
+
+#[test]
+fn merges_runs_with_the_same_style() {
+    let doc = DocxBuilder::new()
+        .body(
+            "<w:p>\
+             <w:r><w:t>one </w:t></w:r><w:r><w:t>two </w:t></w:r>\
+             <w:r><w:rPr><w:b/></w:rPr><w:t>three </w:t></w:r><w:r><w:rPr><w:b/></w:rPr><w:t>four</w:t></w:r>\
+             </w:p>",
+        )
+        .load();
+    assert_eq!(texts(&doc.paras[0].runs), vec!["one two ", "three four"]);
+}
+
+#[test]
+fn skips_empty_runs() {
+    let doc = DocxBuilder::new()
+        .body(
+            "<w:p><w:r><w:t>a</w:t></w:r><w:r><w:rPr><w:b/></w:rPr><w:t></w:t></w:r><w:r><w:t>b</w:t></w:r></w:p>",
+        )
+        .load();
+    // without the empty bold run in between, a and b merge
+    assert_eq!(texts(&doc.paras[0].runs), vec!["ab"]);
+}
+
+#[test]
+fn removes_newlines_and_tabs() {
+    let doc = DocxBuilder::new()
+        .body("<w:p><w:r><w:t>line\none\r\n\tindented  twice</w:t></w:r></w:p>")
+        .load();
+    // spaces are kept, including repeated ones
+    assert_eq!(texts(&doc.paras[0].runs), vec!["lineoneindented  twice"]);
+}
+
+#[test]
+fn unescapes_entities() {
+    let doc = DocxBuilder::new()
+        .body("<w:p><w:r><w:t>A &amp; B &lt;C&gt; &quot;D&quot;</w:t></w:r></w:p>")
+        .load();
+    assert_eq!(texts(&doc.paras[0].runs), vec![r#"A & B <C> "D""#]);
+}
+
+#[test]
+fn outline_level_from_paragraph_style() {
+    let doc = DocxBuilder::new()
+        .styles(
+            &[
+                heading_style("Heading1", 0, "<w:b/>"),
+                heading_style("Heading4", 3, ""),
+            ]
+            .concat(),
+        )
+        .body(
+            r#"<w:p><w:pPr><w:pStyle w:val="Heading1"/></w:pPr><w:r><w:t>Pocket</w:t></w:r></w:p><w:p><w:r><w:t>body</w:t></w:r></w:p><w:p><w:pPr><w:pStyle w:val="Heading4"/></w:pPr><w:r><w:t>Tag</w:t></w:r></w:p>"#,
+        )
+        .load();
+    let levels: Vec<Option<u32>> = doc.paras.iter().map(|para| para.outline_level).collect();
+    assert_eq!(levels, vec![Some(0), None, Some(3)]);
+    assert_eq!(doc.paras[0].runs[0].style.bold, Some(true));
+}
+
+#[test]
+fn outline_level_from_direct_formatting() {
+    let doc = DocxBuilder::new()
+        .body(r#"<w:p><w:pPr><w:outlineLvl w:val="2"/></w:pPr><w:r><w:t>Block</w:t></w:r></w:p>"#)
+        .load();
+    assert_eq!(doc.paras[0].outline_level, Some(2));
+}
+
+#[test]
+fn headings_are_copied_to_outline() {
+    let doc = DocxBuilder::new()
+        .styles(&heading_style("Heading1", 0, ""))
+        .body(
+            r#"<w:p><w:r><w:t>intro</w:t></w:r></w:p><w:p><w:pPr><w:pStyle w:val="Heading1"/></w:pPr><w:r><w:t>First</w:t></w:r></w:p><w:p><w:r><w:t>body</w:t></w:r></w:p><w:p><w:pPr><w:pStyle w:val="Heading1"/></w:pPr><w:r><w:t>Second</w:t></w:r></w:p>"#,
+        )
+        .load();
+    assert_eq!(doc.outline_paras.len(), 2);
+    let outline: Vec<(usize, usize, Vec<&str>)> = doc
+        .outline_paras
+        .iter()
+        .map(|para| (para.index, para.link, texts(&para.runs)))
+        .collect();
+    assert_eq!(outline, vec![(0, 1, vec!["First"]), (1, 3, vec!["Second"])]);
+}
+
+#[test]
+fn missing_document_part_is_a_warning() {
+    let doc = DocxBuilder::new().without_document().load();
+    assert!(doc.paras.is_empty());
+    assert_eq!(
+        doc.warnings,
+        vec!["word/document.xml is missing, the text was lost"]
+    );
+}
+
+#[test]
+fn keeps_paragraphs_before_malformed_xml() {
+    let doc = DocxBuilder::new()
+        .body("<w:p><w:r><w:t>kept</w:t></w:r></w:p><w:p><w:r><w:t>lost</w:t></w:p>")
+        .load();
+    assert_eq!(doc.paras.len(), 1);
+    assert_eq!(texts(&doc.paras[0].runs), vec!["kept"]);
+    assert_eq!(doc.warnings.len(), 1);
+}
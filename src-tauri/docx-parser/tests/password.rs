@@ -0,0 +1,43 @@
+mod common;
+
+use common::{outline_levels, seed};
+use docx_parser::{Document, LoadError};
+
+#[test]
+fn decrypts_with_the_password() {
+    let mut doc = Document::new();
+    doc.load_bytes_with_password(&seed("encrypted.docx"), Some("password"))
+        .unwrap();
+    let paras: Vec<String> = doc.paras.iter().map(|para| para.text()).collect();
+    assert_eq!(
+        paras,
+        vec![
+            "Aff",
+            "Warming is real & anthropogenic",
+            "Smith 22 (professor, \"Climate\")",
+            "The evidence is overwhelmingand growing",
+            "Block",
+        ]
+    );
+    assert_eq!(
+        outline_levels(&doc),
+        vec![Some(0), Some(3), None, None, Some(1)]
+    );
+    assert!(doc.warnings.is_empty());
+}
+
+#[test]
+fn wrong_password_is_an_error() {
+    let mut doc = Document::new();
+    let result = doc.load_bytes_with_password(&seed("encrypted.docx"), Some("hunter2"));
+    assert!(matches!(result, Err(LoadError::WrongPassword)));
+    assert!(doc.paras.is_empty());
+}
+
+#[test]
+fn password_is_required() {
+    let mut doc = Document::new();
+    let result = doc.load_bytes(&seed("encrypted.docx"));
+    assert!(matches!(result, Err(LoadError::PasswordRequired)));
+    assert!(doc.paras.is_empty());
+}
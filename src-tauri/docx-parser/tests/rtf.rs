@@ -0,0 +1,91 @@
+mod common;
+
+use common::{outline_levels, plain, seed, texts};
+use docx_parser::{Document, Style};
+
+fn load(rtf: &str) -> Document {
+    let mut doc = Document::new();
+    doc.load_bytes(rtf.as_bytes()).unwrap();
+    doc
+}
+
+#[test]
+fn reads_small_file() {
+    let mut doc = Document::new();
+    doc.load_bytes(&seed("small.rtf")).unwrap();
+    let paras: Vec<Vec<&str>> = doc.paras.iter().map(|para| texts(&para.runs)).collect();
+    // the font table, colour table, stylesheet and generator aren't text
+    assert_eq!(
+        paras,
+        vec![
+            vec!["Big Tag"],
+            vec!["Normal ", "bold", " and ", "under", " ", "café", " “quote”"],
+            vec!["Block"],
+        ]
+    );
+    assert_eq!(outline_levels(&doc), vec![Some(0), None, Some(2)]);
+    assert_eq!(doc.outline_paras.len(), 2);
+    let heading = &doc.paras[0].runs[0].style;
+    assert_eq!(
+        heading,
+        &Style {
+            bold: Some(true),
+            size: Some(32),
+            ..plain()
+        }
+    );
+    let runs = &doc.paras[1].runs;
+    assert_eq!(runs[1].style.bold, Some(true));
+    // from the \cs10 character style
+    assert_eq!(runs[3].style.underline, Some(true));
+    assert_eq!(runs[5].style.highlight, Some(true));
+    assert_eq!(runs[6].style, plain());
+}
+
+#[test]
+fn styles_inherit_from_based_on() {
+    let doc = load(
+        r"{\rtf1{\stylesheet{\s0 Normal;}{\s1\outlinelevel1\b\fs28 heading 2;}{\s2\sbasedon1\b0 Quiet Heading;}{\s3\sbasedon2\outlinelevel3 Deeper;}}
+\pard\s2 quiet\par
+\pard\s3 deeper\par
+}",
+    );
+    assert_eq!(texts(&doc.paras[0].runs), vec!["quiet"]);
+    assert_eq!(outline_levels(&doc), vec![Some(1), Some(3)]);
+    assert_eq!(
+        doc.style_map["s2"].style,
+        Style {
+            bold: Some(false),
+            size: Some(28),
+            ..plain()
+        }
+    );
+    assert_eq!(doc.style_map["s3"].outline_level, Some(3));
+    assert_eq!(doc.style_map["s3"].style, doc.style_map["s2"].style);
+}
+
+#[test]
+fn outline_level_is_per_paragraph() {
+    let doc = load(
+        r"{\rtf1{\stylesheet{\s0 Normal;}{\s1\outlinelevel0 heading 1;}}
+\pard\s1\outlinelevel4 overridden\par
+\pard\outlinelevel1 direct\par
+kept\par
+\pard reset\par
+\pard\outlinelevel9 too deep\par
+\pard\s1 style\par
+}",
+    );
+    // \outlinelevel carries over to the next paragraph until \pard, like word
+    assert_eq!(
+        outline_levels(&doc),
+        vec![Some(4), Some(1), Some(1), None, None, Some(0)]
+    );
+}
+
+#[test]
+fn missing_styles_are_plain() {
+    let doc = load(r"{\rtf1\pard\s7 unknown {\cs9 style}\par}");
+    assert_eq!(outline_levels(&doc), vec![None]);
+    assert!(doc.paras[0].runs.iter().all(|run| run.style == plain()));
+}
@@ -0,0 +1,464 @@
+mod common;
+
+use common::{heading_style, texts, DocxBuilder};
+use docx_parser::{Document, Query, QueryError, Search, SearchResult};
+
+use std::ops::Range;
+
+fn load(paras: &[&str]) -> Document {
+    let body: String = paras
+        .iter()
+        .map(|text| {
+            format!(
+                "<w:p><w:r><w:t xml:space=\"preserve\">{}</w:t></w:r></w:p>",
+                text
+            )
+        })
+        .collect();
+    DocxBuilder::new().body(&body).load()
+}
+
+fn text(text: &str) -> Query {
+    Query {
+        text: text.to_string(),
+        match_case: false,
+        only_outline: false,
+        regex: false,
+        whole_word: false,
+        ignore_diacritics: false,
+    }
+}
+
+fn regex(text: &str) -> Query {
+    Query {
+        regex: true,
+        ..self::text(text)
+    }
+}
+
+// (paragraph, start, end) of every result
+fn spans(doc: &Document, query: &Query) -> Vec<(usize, usize, usize)> {
+    let mut search = Search::new();
+    search.set_paras(&doc.paras);
+    search
+        .search(query, &doc.paras, 0, usize::MAX)
+        .unwrap()
+        .iter()
+        .map(|result| (result.link, result.start, result.end))
+        .collect()
+}
+
+#[test]
+fn regex_finds_years() {
+    let doc = load(&["Smith 1998 and Jones 2021", "page 12345", "in 2003"]);
+    assert_eq!(
+        spans(&doc, &regex(r"\b(19|20)\d\d\b")),
+        vec![(0, 6, 10), (0, 21, 25), (2, 3, 7)]
+    );
+}
+
+#[test]
+fn regex_spans_count_characters() {
+    // the curly quote is three bytes but one character
+    let doc = load(&["“Smith ’98 says"]);
+    assert_eq!(spans(&doc, &regex(r"\w+ ’\d\d")), vec![(0, 1, 10)]);
+}
+
+#[test]
+fn regex_follows_match_case() {
+    let doc = load(&["Nuclear nuclear"]);
+    assert_eq!(spans(&doc, &regex("nuc\\w+")), vec![(0, 0, 7), (0, 8, 15)]);
+    let query = Query {
+        match_case: true,
+        ..regex("nuc\\w+")
+    };
+    assert_eq!(spans(&doc, &query), vec![(0, 8, 15)]);
+}
+
+#[test]
+fn invalid_regex_is_an_error() {
+    let doc = load(&["text"]);
+    let mut search = Search::new();
+    search.set_paras(&doc.paras);
+    search.search(&regex("te"), &doc.paras, 0, 10).unwrap();
+    let error = search.search(&regex("(te"), &doc.paras, 0, 10).unwrap_err();
+    assert!(matches!(error, QueryError::Regex(_)));
+    assert!(error.to_string().contains("unclosed group"));
+    // the last good query is kept
+    assert_eq!(search.last_query(), Some(&regex("te")));
+    assert_eq!(
+        search
+            .search(&regex("te"), &doc.paras, 0, 10)
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn longer_regex_doesnt_narrow() {
+    let doc = load(&["alpha", "beta"]);
+    let mut search = Search::new();
+    search.set_paras(&doc.paras);
+    assert_eq!(
+        search.search(&regex("a"), &doc.paras, 0, 10).unwrap().len(),
+        3
+    );
+    // contains the last query but matches more
+    let results = search.search(&regex("a|b"), &doc.paras, 0, 10).unwrap();
+    assert_eq!(results.len(), 4);
+}
+
+#[test]
+fn offsets_are_in_the_original_text() {
+    // "İ" lowercases to two characters, which used to shift every match after it
+    let doc = load(&["İSTANBUL and istanbul"]);
+    assert_eq!(spans(&doc, &text("stanbul")), vec![(0, 1, 8), (0, 14, 21)]);
+    assert_eq!(spans(&doc, &text("and")), vec![(0, 9, 12)]);
+}
+
+#[test]
+fn folds_case_fully() {
+    let doc = load(&["Die Straße", "ΣΟΦΟΣ σοφος"]);
+    assert_eq!(spans(&doc, &text("STRASSE")), vec![(0, 4, 10)]);
+    assert_eq!(spans(&doc, &text("σοφοσ")), vec![(1, 0, 5), (1, 6, 11)]);
+    let query = Query {
+        match_case: true,
+        ..text("STRASSE")
+    };
+    assert!(spans(&doc, &query).is_empty());
+}
+
+#[test]
+fn normalises_quotes_dashes_and_ligatures() {
+    let doc = load(&["Smith ’98 — “ﬁrst” strike"]);
+    assert_eq!(
+        spans(&doc, &text("smith '98 - \"first\"")),
+        vec![(0, 0, 18)]
+    );
+    // the ligature is one character in the document
+    assert_eq!(spans(&doc, &text("fi")), vec![(0, 13, 14)]);
+}
+
+#[test]
+fn diacritics_are_optional() {
+    let doc = load(&["café and cafe\u{301} and cafe"]);
+    // composed and decomposed accents are the same letter
+    assert_eq!(spans(&doc, &text("café")), vec![(0, 0, 4), (0, 9, 14)]);
+    let query = Query {
+        ignore_diacritics: true,
+        ..text("cafe")
+    };
+    assert_eq!(
+        spans(&doc, &query),
+        vec![(0, 0, 4), (0, 9, 14), (0, 19, 23)]
+    );
+    // an unaccented query doesn't match half of an accented letter
+    assert_eq!(spans(&doc, &text("cafe")), vec![(0, 19, 23)]);
+}
+
+#[test]
+fn whole_word_skips_parts_of_words() {
+    let doc = load(&["warrant war, war-torn wars '98"]);
+    let query = Query {
+        whole_word: true,
+        ..text("war")
+    };
+    assert_eq!(spans(&doc, &query), vec![(0, 8, 11), (0, 13, 16)]);
+    let query = Query {
+        whole_word: true,
+        ..text("'98")
+    };
+    assert_eq!(spans(&doc, &query), vec![(0, 27, 30)]);
+    let query = Query {
+        whole_word: true,
+        ..regex("wa\\w")
+    };
+    assert_eq!(spans(&doc, &query), vec![(0, 8, 11), (0, 13, 16)]);
+}
+
+#[test]
+fn results_say_which_runs_match() {
+    let doc = DocxBuilder::new()
+        .body(
+            "<w:p><w:r><w:t xml:space=\"preserve\">nuclear </w:t></w:r>\
+             <w:r><w:rPr><w:b/></w:rPr><w:t xml:space=\"preserve\">war causes </w:t></w:r>\
+             <w:r><w:t>extinction</w:t></w:r></w:p>",
+        )
+        .load();
+    let mut search = Search::new();
+    search.set_paras(&doc.paras);
+    let results = search.search(&text("war"), &doc.paras, 0, 10).unwrap();
+    assert_eq!((results[0].start, results[0].end), (8, 11));
+    assert_eq!(results[0].runs, 1..2);
+    let results = search
+        .search(&text("causes extinct"), &doc.paras, 0, 10)
+        .unwrap();
+    assert_eq!(results[0].runs, 1..3);
+    // short paragraphs are shown whole, formatting and all
+    let snippet = &results[0].snippet;
+    assert_eq!(texts(&snippet.runs), texts(&doc.paras[0].runs));
+    assert_eq!(snippet.runs[1].style.bold, Some(true));
+    assert_eq!(snippet.start, 0);
+    assert!(!snippet.cut_start && !snippet.cut_end);
+}
+
+#[test]
+fn long_paragraphs_are_cut_at_words() {
+    let words: Vec<String> = (0..200).map(|i| format!("word{}", i)).collect();
+    let doc = load(&[&words.join(" ")]);
+    let mut search = Search::new();
+    search.set_paras(&doc.paras);
+    let result = &search.search(&text("word100 "), &doc.paras, 0, 10).unwrap()[0];
+    let snippet = &result.snippet;
+    assert!(snippet.cut_start && snippet.cut_end);
+    let snippet_text: String = texts(&snippet.runs).concat();
+    assert!(snippet_text.starts_with("word"));
+    assert!(snippet_text.ends_with(char::is_numeric));
+    assert!(snippet_text.chars().count() < 250);
+    // the match is where the offsets say
+    let chars: Vec<char> = snippet_text.chars().collect();
+    let matched: String = chars[result.start - snippet.start..result.end - snippet.start]
+        .iter()
+        .collect();
+    assert_eq!(matched, "word100 ");
+}
+
+// small deterministic generator, so a failing case fails every time
+struct Random(u64);
+impl Random {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 33) as usize % n
+    }
+    fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.below(items.len())]
+    }
+}
+
+// words that fold into each other in every way a query can fold them
+const WORDS: &[&str] = &[
+    "war",
+    "warrant",
+    "War",
+    "WAR",
+    "wars",
+    "nuclear",
+    "Nuclear",
+    "café",
+    "cafe\u{301}",
+    "cafe",
+    "Straße",
+    "STRASSE",
+    "’98",
+    "'98",
+    "—",
+    "-",
+    "“deter”",
+    "\"deter\"",
+    "ﬁrst",
+    "first",
+    "İstanbul",
+    "σοφος",
+    "ΣΟΦΟΣ",
+    "the",
+    "a",
+    "1998",
+    "2021",
+];
+
+fn generate(random: &mut Random) -> Document {
+    let mut body = String::new();
+    for _ in 0..150 {
+        body.push_str("<w:p>");
+        match random.below(6) {
+            0 => body.push_str(r#"<w:pPr><w:pStyle w:val="Heading1"/></w:pPr>"#),
+            1 => body.push_str(r#"<w:pPr><w:pStyle w:val="Heading2"/></w:pPr>"#),
+            _ => {}
+        }
+        for _ in 0..1 + random.below(4) {
+            let words: Vec<&str> = (0..1 + random.below(8))
+                .map(|_| random.pick(WORDS))
+                .collect();
+            let bold = match random.below(2) {
+                0 => "<w:rPr><w:b/></w:rPr>",
+                _ => "",
+            };
+            body.push_str(&format!(
+                r#"<w:r>{}<w:t xml:space="preserve">{} </w:t></w:r>"#,
+                bold,
+                words.join(" ")
+            ));
+        }
+        body.push_str("</w:p>");
+    }
+    let styles = heading_style("Heading1", 0, "") + &heading_style("Heading2", 1, "");
+    DocxBuilder::new().styles(&styles).body(&body).load()
+}
+
+// everything about a result but its snippet's formatting
+type Key = (usize, usize, usize, Range<usize>, Range<usize>, String);
+
+fn key(results: &[SearchResult]) -> Vec<Key> {
+    results
+        .iter()
+        .map(|result| {
+            (
+                result.link,
+                result.index,
+                result.query_index,
+                result.start..result.end,
+                result.runs.clone(),
+                texts(&result.snippet.runs).concat(),
+            )
+        })
+        .collect()
+}
+
+// types each phrase a character at a time, sometimes changing an option or
+// backspacing, and checks every page against a new search for the same query
+fn compare_with_fresh_search(seed: u64, phrases: &[&str]) {
+    let mut random = Random(seed);
+    let doc = generate(&mut random);
+    let mut search = Search::new();
+    search.set_paras(&doc.paras);
+    let mut query = text("");
+    for phrase in phrases {
+        let chars: Vec<char> = phrase.chars().collect();
+        let mut typed = 0;
+        while typed < chars.len() {
+            match random.below(12) {
+                0 => query.match_case = !query.match_case,
+                1 => query.only_outline = !query.only_outline,
+                2 => query.whole_word = !query.whole_word,
+                3 => query.ignore_diacritics = !query.ignore_diacritics,
+                4 => query.regex = !query.regex,
+                _ => {}
+            }
+            // backspacing sometimes goes down to nothing, which nothing matches
+            match random.below(6) {
+                0 => typed = typed.saturating_sub(2),
+                _ => typed += 1,
+            }
+            query.text = chars[..typed].iter().collect();
+            // the sidebar asks for pages as it scrolls, so the last search
+            // has only looked at part of the document
+            let i = random.below(40);
+            let j = match random.below(4) {
+                0 => usize::MAX,
+                _ => i + random.below(40),
+            };
+            let mut fresh = Search::new();
+            fresh.set_paras(&doc.paras);
+            match (
+                search.search(&query, &doc.paras, i, j),
+                fresh.search(&query, &doc.paras, i, j),
+            ) {
+                (Ok(narrowed), Ok(expected)) => {
+                    assert_eq!(key(&narrowed), key(&expected), "{:?} {}..{}", query, i, j)
+                }
+                (Err(_), Err(_)) => {}
+                (narrowed, expected) => panic!(
+                    "{:?}: {:?} but a new search gives {:?}",
+                    query,
+                    narrowed.map(|results| results.len()),
+                    expected.map(|results| results.len())
+                ),
+            }
+        }
+        // the whole list, after everything searched before it
+        let mut fresh = Search::new();
+        fresh.set_paras(&doc.paras);
+        assert_eq!(
+            key(&search
+                .search(&query, &doc.paras, 0, usize::MAX)
+                .unwrap_or_default()),
+            key(&fresh
+                .search(&query, &doc.paras, 0, usize::MAX)
+                .unwrap_or_default()),
+            "{:?}",
+            query
+        );
+    }
+}
+
+#[test]
+fn narrowing_matches_a_fresh_search() {
+    for seed in 0..20 {
+        compare_with_fresh_search(
+            seed,
+            &[
+                "nuclear war",
+                "warrant",
+                "café",
+                "Straße",
+                "’98 — “deter”",
+                "first",
+                "İstanbul",
+                "σοφος",
+            ],
+        );
+    }
+}
+
+#[test]
+fn narrowing_with_changed_options_matches_a_fresh_search() {
+    let doc = generate(&mut Random(7));
+    let queries = [
+        text("war"),
+        Query {
+            match_case: true,
+            ..text("War")
+        },
+        Query {
+            match_case: true,
+            ..text("Wars")
+        },
+        Query {
+            only_outline: true,
+            ..text("war")
+        },
+        text("warr"),
+        Query {
+            whole_word: true,
+            ..text("war")
+        },
+        Query {
+            whole_word: true,
+            ..text("wars")
+        },
+        Query {
+            ignore_diacritics: true,
+            ..text("caf")
+        },
+        text("cafe"),
+        // the accent joins the e, so this doesn't match where cafe did
+        text("cafe\u{301}"),
+        Query {
+            ignore_diacritics: true,
+            ..text("café")
+        },
+        regex("19"),
+        regex("19\\d\\d"),
+        text("STRASSE"),
+        Query {
+            match_case: true,
+            ..text("Straße")
+        },
+    ];
+    let mut search = Search::new();
+    search.set_paras(&doc.paras);
+    for query in queries.iter() {
+        let mut fresh = Search::new();
+        fresh.set_paras(&doc.paras);
+        assert_eq!(
+            key(&search.search(query, &doc.paras, 0, usize::MAX).unwrap()),
+            key(&fresh.search(query, &doc.paras, 0, usize::MAX).unwrap()),
+            "{:?}",
+            query
+        );
+    }
+}
@@ -0,0 +1,107 @@
+mod common;
+
+use common::{heading_style, plain, style, DocxBuilder};
+use docx_parser::Style;
+
+#[test]
+fn reads_run_properties() {
+    let doc = DocxBuilder::new()
+        .styles(&style(
+            "Card",
+            None,
+            r#"<w:b/><w:u w:val="single"/><w:highlight w:val="cyan"/><w:sz w:val="22"/>"#,
+        ))
+        .load();
+    assert_eq!(
+        doc.style_map["Card"].style,
+        Style {
+            bold: Some(true),
+            underline: Some(true),
+            highlight: Some(true),
+            size: Some(22),
+        }
+    );
+    assert_eq!(doc.style_map["Card"].outline_level, None);
+}
+
+#[test]
+fn ignores_disabled_properties() {
+    let doc = DocxBuilder::new()
+        .styles(&style(
+            "Plain",
+            None,
+            r#"<w:b w:val="0"/><w:u w:val="none"/>"#,
+        ))
+        .load();
+    assert_eq!(doc.style_map["Plain"].style, plain());
+}
+
+#[test]
+fn reads_outline_level() {
+    let doc = DocxBuilder::new()
+        .styles(&heading_style("Heading2", 1, "<w:b/>"))
+        .load();
+    assert_eq!(doc.style_map["Heading2"].outline_level, Some(1));
+    assert_eq!(doc.style_map["Heading2"].style.bold, Some(true));
+}
+
+#[test]
+fn inherits_from_based_on_style() {
+    let doc = DocxBuilder::new()
+        .styles(
+            &[
+                style("Normal", None, r#"<w:sz w:val="22"/>"#),
+                style("Cite", Some("Normal"), "<w:b/>"),
+                style("Underline", Some("Cite"), r#"<w:u w:val="single"/>"#),
+            ]
+            .concat(),
+        )
+        .load();
+    assert_eq!(
+        doc.style_map["Underline"].style,
+        Style {
+            bold: Some(true),
+            underline: Some(true),
+            highlight: None,
+            size: Some(22),
+        }
+    );
+}
+
+#[test]
+fn own_properties_override_based_on_style() {
+    let doc = DocxBuilder::new()
+        .styles(
+            &[
+                style("Big", None, r#"<w:sz w:val="32"/>"#),
+                style("Small", Some("Big"), r#"<w:sz w:val="16"/>"#),
+            ]
+            .concat(),
+        )
+        .load();
+    assert_eq!(doc.style_map["Small"].style.size, Some(16));
+    assert_eq!(doc.style_map["Big"].style.size, Some(32));
+}
+
+#[test]
+fn ignores_unknown_based_on_style() {
+    let doc = DocxBuilder::new()
+        .styles(&style("Orphan", Some("Missing"), "<w:b/>"))
+        .load();
+    assert_eq!(doc.style_map["Orphan"].style.bold, Some(true));
+    assert!(doc.warnings.is_empty());
+}
+
+#[test]
+fn missing_styles_part_is_a_warning() {
+    let doc = DocxBuilder::new()
+        .without_styles()
+        .body("<w:p><w:r><w:t>text</w:t></w:r></w:p>")
+        .load();
+    assert!(doc.style_map.is_empty());
+    assert_eq!(doc.paras.len(), 1);
+    assert_eq!(
+        doc.warnings,
+        vec!["word/styles.xml is missing, formatting was lost"]
+    );
+}
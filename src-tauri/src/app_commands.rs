@@ -8,6 +8,9 @@ use open;
 use std::collections::HashMap;
 use std::sync::Mutex;
 
+// file types the open dialogs accept
+pub const DOCUMENT_EXTENSIONS: &[&str] = &["docx", "odt", "rtf", "xml"];
+
 #[derive(Clone, serde::Serialize)]
 pub struct Payload {
   pub message: String,
@@ -47,7 +50,7 @@ pub fn get_window_fullscreen_state(window: tauri::Window) -> bool {
 #[tauri::command]
 pub async fn open_dialog() -> Result<Vec<PathBuf>, bool> {
   if let Some(paths) = FileDialogBuilderBlocking::new()
-    .add_filter("Word Document", &["docx"])
+    .add_filter("Document", DOCUMENT_EXTENSIONS)
     .pick_files()
   {
     Ok(paths)
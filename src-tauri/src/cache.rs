@@ -0,0 +1,127 @@
+use docx_parser::{Document, PARSER_VERSION};
+use filetime::FileTime;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+// total size the cache directory is allowed to grow to before old entries are evicted
+pub const MAX_CACHE_SIZE: u64 = 256 * 1024 * 1024;
+
+pub struct Cache {
+  pub dir: Option<PathBuf>,
+  pub max_size: u64,
+}
+pub struct DocumentCache(pub Mutex<Cache>);
+
+impl Cache {
+  pub fn new(dir: Option<PathBuf>, max_size: u64) -> Cache {
+    if let Some(dir) = &dir {
+      if let Err(e) = fs::create_dir_all(dir) {
+        println!("couldn't create cache dir {:?}: {}", dir, e);
+      }
+    }
+    Cache { dir, max_size }
+  }
+  // key is the hash of the file contents plus the parser version,
+  // so edited files and parser changes both miss
+  pub fn key(bytes: &[u8]) -> String {
+    let hash = Sha256::digest(bytes);
+    let mut key = String::with_capacity(hash.len() * 2 + 8);
+    for byte in hash.iter() {
+      key.push_str(&format!("{:02x}", byte));
+    }
+    key.push_str(&format!("-v{}", PARSER_VERSION));
+    key
+  }
+  fn entry_path(&self, key: &str) -> Option<PathBuf> {
+    self
+      .dir
+      .as_ref()
+      .map(|dir| dir.join(key.to_string() + ".bin"))
+  }
+  pub fn get(&self, key: &str) -> Option<Document> {
+    let path = self.entry_path(key)?;
+    let bytes = fs::read(&path).ok()?;
+    match bincode::deserialize(&bytes) {
+      Ok(doc) => {
+        // eviction goes by mtime, so mark the entry as just used
+        filetime::set_file_mtime(&path, FileTime::now()).ok();
+        Some(doc)
+      }
+      Err(e) => {
+        // corrupt or outdated entry, drop it so it gets rewritten
+        println!("couldn't read cache entry {:?}: {}", path, e);
+        fs::remove_file(&path).ok();
+        None
+      }
+    }
+  }
+  pub fn put(&self, key: &str, doc: &Document) {
+    let path = match self.entry_path(key) {
+      Some(path) => path,
+      None => return,
+    };
+    let bytes = match bincode::serialize(doc) {
+      Ok(bytes) => bytes,
+      Err(e) => {
+        println!("couldn't serialize document for cache: {}", e);
+        return;
+      }
+    };
+    // write to a temporary file first so a crash never leaves half an entry
+    let tmp_path = path.with_extension("tmp");
+    if fs::write(&tmp_path, &bytes).is_err() || fs::rename(&tmp_path, &path).is_err() {
+      println!("couldn't write cache entry {:?}", path);
+      fs::remove_file(&tmp_path).ok();
+      return;
+    }
+    self.evict();
+  }
+  // remove the least recently used entries until the cache fits in max_size
+  pub fn evict(&self) {
+    let mut entries = self.entries();
+    let mut total: u64 = entries.iter().map(|entry| entry.1).sum();
+    if total <= self.max_size {
+      return;
+    }
+    entries.sort_by_key(|entry| entry.2);
+    for (path, size, _) in entries {
+      if total <= self.max_size {
+        break;
+      }
+      println!("evicting cache entry {:?}", path);
+      if fs::remove_file(&path).is_ok() {
+        total -= size;
+      }
+    }
+  }
+  pub fn clear(&self) {
+    for (path, _, _) in self.entries() {
+      fs::remove_file(&path).ok();
+    }
+  }
+  fn entries(&self) -> Vec<(PathBuf, u64, SystemTime)> {
+    let mut entries = Vec::new();
+    let dir = match &self.dir {
+      Some(dir) => dir,
+      None => return entries,
+    };
+    let read_dir = match fs::read_dir(dir) {
+      Ok(read_dir) => read_dir,
+      Err(_) => return entries,
+    };
+    for entry in read_dir.flatten() {
+      let path = entry.path();
+      if path.extension().map_or(true, |ext| ext != "bin") {
+        continue;
+      }
+      if let Ok(metadata) = entry.metadata() {
+        let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        entries.push((path, metadata.len(), modified));
+      }
+    }
+    entries
+  }
+}
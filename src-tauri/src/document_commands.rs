@@ -1,48 +1,193 @@
-use crate::document::Document;
-use crate::document::OutlinePara;
-use crate::document::Para;
+use crate::cache::{Cache, DocumentCache};
+use crate::watch::{unwatch_file, watch_file, FileWatchers};
 
-use serde::{Deserialize, Serialize};
+use docx_parser::{
+  Document, DocumentInfo, LoadError, OutlinePara, Para, Query, Search, SearchResult,
+};
+use serde::Serialize;
 use std::cmp;
-use std::cmp::PartialEq;
 use std::collections::HashMap;
-use std::sync::Mutex;
+use std::fs;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
 use tauri::State;
 use tauri::Window;
 
-#[derive(Clone, PartialEq, Deserialize)]
-pub struct Query {
-  pub text: String,
-  pub match_case: bool,
-  pub only_outline: bool,
-}
-#[derive(Clone, Serialize, Deserialize)]
-pub struct SearchResult {
-  pub link: usize,
-  pub index: usize,
-  pub para: Para,
-  pub query_index: usize,
-}
-
 pub struct Paras(pub Mutex<HashMap<String, Vec<Para>>>);
 pub struct OutlineParas(pub Mutex<HashMap<String, Vec<OutlinePara>>>);
-pub struct SearchResultsState {
-  pub results: Vec<SearchResult>,
-  pub last_query: Option<Query>,
-  pub para_texts: Vec<String>,
+pub struct SearchResults(pub Mutex<HashMap<String, Search>>);
+// only windows with an open file have an entry
+pub struct DocumentInfos(pub Mutex<HashMap<String, DocumentInfo>>);
+// the file open in each window, with the bytes it was parsed from, so sections
+// are copied out of the same version of the file the window shows
+pub struct OpenFile {
+  pub path: String,
+  pub bytes: Arc<Vec<u8>>,
 }
+pub struct OpenFiles(pub Mutex<HashMap<String, OpenFile>>);
 
-pub struct SearchResults(pub Mutex<HashMap<String, SearchResultsState>>);
+#[derive(Clone, Serialize)]
+pub struct LoadResult {
+  pub info: DocumentInfo,
+  // damaged files still open, with a list of what was lost
+  pub warnings: Vec<String>,
+}
+
+// why a file didn't open. the frontend goes by the kind, the message is for people
+#[derive(Clone, Serialize)]
+pub struct LoadFailure {
+  pub kind: LoadFailureKind,
+  pub message: String,
+}
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoadFailureKind {
+  PasswordRequired,
+  WrongPassword,
+  Other,
+}
+impl From<LoadError> for LoadFailure {
+  fn from(e: LoadError) -> LoadFailure {
+    let kind = match e {
+      LoadError::PasswordRequired => LoadFailureKind::PasswordRequired,
+      LoadError::WrongPassword => LoadFailureKind::WrongPassword,
+      _ => LoadFailureKind::Other,
+    };
+    LoadFailure {
+      kind,
+      message: e.to_string(),
+    }
+  }
+}
 
+// the cache is only locked to read and write entries, so a slow parse in one
+// window doesn't hold up opening files in the others
+pub fn parse_bytes(
+  bytes: &[u8],
+  password: Option<&str>,
+  cache: &DocumentCache,
+) -> Result<Document, LoadError> {
+  // never write decrypted documents to disk
+  if password.is_some() {
+    let mut doc = Document::new();
+    doc.load_bytes_with_password(bytes, password)?;
+    return Ok(doc);
+  }
+  let key = Cache::key(bytes);
+  let cached = cache.0.lock().unwrap().get(&key);
+  match cached {
+    Some(doc) => {
+      println!("using cached parse");
+      Ok(doc)
+    }
+    None => {
+      let mut doc = Document::new();
+      doc.load_bytes(bytes)?;
+      cache.0.lock().unwrap().put(&key, &doc);
+      Ok(doc)
+    }
+  }
+}
+pub fn set_document(
+  doc: Document,
+  paras: &mut Vec<Para>,
+  outline_paras: &mut Vec<OutlinePara>,
+  search_results: &mut Search,
+) {
+  paras.clear();
+  outline_paras.clear();
+  search_results.set_paras(&doc.paras);
+  for para in doc.paras {
+    paras.push(para);
+  }
+  for outline_para in doc.outline_paras {
+    outline_paras.push(outline_para);
+  }
+}
 #[tauri::command]
 pub fn load_file(
   path: String,
   paras: State<Paras>,
   outline_paras: State<OutlineParas>,
   search_results: State<SearchResults>,
+  document_infos: State<DocumentInfos>,
+  open_files: State<OpenFiles>,
+  cache: State<DocumentCache>,
+  watchers: State<FileWatchers>,
   window: Window,
-) -> bool {
+) -> Result<LoadResult, LoadFailure> {
+  open_file(
+    path,
+    None,
+    paras,
+    outline_paras,
+    search_results,
+    document_infos,
+    open_files,
+    cache,
+    watchers,
+    window,
+  )
+}
+#[tauri::command]
+pub fn load_encrypted_file(
+  path: String,
+  password: String,
+  paras: State<Paras>,
+  outline_paras: State<OutlineParas>,
+  search_results: State<SearchResults>,
+  document_infos: State<DocumentInfos>,
+  open_files: State<OpenFiles>,
+  cache: State<DocumentCache>,
+  watchers: State<FileWatchers>,
+  window: Window,
+) -> Result<LoadResult, LoadFailure> {
+  open_file(
+    path,
+    Some(password),
+    paras,
+    outline_paras,
+    search_results,
+    document_infos,
+    open_files,
+    cache,
+    watchers,
+    window,
+  )
+}
+fn open_file(
+  path: String,
+  password: Option<String>,
+  paras: State<Paras>,
+  outline_paras: State<OutlineParas>,
+  search_results: State<SearchResults>,
+  document_infos: State<DocumentInfos>,
+  open_files: State<OpenFiles>,
+  cache: State<DocumentCache>,
+  watchers: State<FileWatchers>,
+  window: Window,
+) -> Result<LoadResult, LoadFailure> {
   let label = window.label();
+  // first unload file
+  println!("unloading current file in window {:?}", label);
+  unwatch_file(label, &watchers);
+  // then load new one, set_document clears the old state
+  println!("loading {:?} file in window {:?}", path, label);
+  // the file can be gone or unreadable by the time it's opened
+  let bytes = match fs::read(&path) {
+    Ok(bytes) => bytes,
+    Err(e) => {
+      println!("couldn't read {:?}: {}", path, e);
+      return Err(LoadError::Io(e).into());
+    }
+  };
+  let doc = match parse_bytes(&bytes, password.as_deref(), &cache) {
+    Ok(doc) => doc,
+    Err(e) => {
+      println!("couldn't load {:?}: {}", path, e);
+      return Err(e.into());
+    }
+  };
   let mut paras_dict = paras.0.lock().unwrap();
   let paras = paras_dict.get_mut(label).unwrap();
 
@@ -50,40 +195,48 @@ pub fn load_file(
   let outline_paras = outline_paras_dict.get_mut(label).unwrap();
 
   let mut search_results_dict = search_results.0.lock().unwrap();
-  let mut search_results = search_results_dict.get_mut(label).unwrap();
-  // first unload file
-  println!("unloading current file in window {:?}", label);
-  paras.clear();
-  outline_paras.clear();
-  search_results.results.clear();
-  search_results.last_query = None;
-  search_results.para_texts.clear();
-  // then load new one
-  println!("loading {:?} file in window {:?}", path, label);
-  let mut doc = Document::new();
-  doc.load_file(&path);
-  for para in doc.paras {
-    let mut combined_text: String = "".to_string();
-    for run in para.runs.iter() {
-      combined_text.push_str(&run.text);
-    }
-    search_results.para_texts.push(combined_text);
-    paras.push(para);
+  let search_results = search_results_dict.get_mut(label).unwrap();
+  let result = LoadResult {
+    info: doc.info.clone(),
+    warnings: doc.warnings.clone(),
+  };
+  for warning in result.warnings.iter() {
+    println!("warning: {}", warning);
   }
-  for outline_para in doc.outline_paras {
-    outline_paras.push(outline_para);
+  document_infos
+    .0
+    .lock()
+    .unwrap()
+    .insert(label.to_string(), doc.info.clone());
+  open_files.0.lock().unwrap().insert(
+    label.to_string(),
+    OpenFile {
+      path: path.clone(),
+      bytes: Arc::new(bytes),
+    },
+  );
+  set_document(doc, paras, outline_paras, search_results);
+  // an encrypted file can't be reloaded without asking for the password again
+  if password.is_none() {
+    watch_file(&window, &path, &watchers);
   }
   println!("done loading file in window {:?}", label);
-  true
+  Ok(result)
 }
 #[tauri::command]
 pub fn unload_file(
   paras: State<Paras>,
   outline_paras: State<OutlineParas>,
   search_results: State<SearchResults>,
+  document_infos: State<DocumentInfos>,
+  open_files: State<OpenFiles>,
+  watchers: State<FileWatchers>,
   window: Window,
 ) -> bool {
   let label = window.label();
+  unwatch_file(label, &watchers);
+  document_infos.0.lock().unwrap().remove(label);
+  open_files.0.lock().unwrap().remove(label);
   let mut paras_dict = paras.0.lock().unwrap();
   let paras = paras_dict.get_mut(label).unwrap();
 
@@ -91,12 +244,10 @@ pub fn unload_file(
   let outline_paras = outline_paras_dict.get_mut(label).unwrap();
 
   let mut search_results_dict = search_results.0.lock().unwrap();
-  let mut search_results = search_results_dict.get_mut(label).unwrap();
+  let search_results = search_results_dict.get_mut(label).unwrap();
   paras.clear();
   outline_paras.clear();
-  search_results.results.clear();
-  search_results.last_query = None;
-  search_results.para_texts.clear();
+  search_results.set_paras(&[]);
   return true;
 }
 #[tauri::command]
@@ -107,7 +258,7 @@ pub fn search(
   paras: State<'_, Paras>,
   search_results: State<'_, SearchResults>,
   window: Window,
-) -> Vec<SearchResult> {
+) -> Result<Vec<SearchResult>, String> {
   println!("searching with query: {:?}", query.text);
   let label = window.label();
 
@@ -115,97 +266,42 @@ pub fn search(
   let paras = paras_dict.get_mut(label).unwrap();
 
   let mut search_results_dict = search_results.0.lock().unwrap();
-  let mut search_results = search_results_dict.get_mut(label).unwrap();
+  let search_results = search_results_dict.get_mut(label).unwrap();
 
-  let query_text = match query.match_case {
-    true => query.text.clone(),
-    false => query.text.to_lowercase(),
-  };
-  // decide what to do with search_results.results
-  if search_results.last_query.is_some() {
-    let last_query = search_results.last_query.as_ref().unwrap();
-    // if queries are the same, we can keep everything
-    if &query == last_query {
-      println!("reused old searches")
-    } else
-    // if last query is smaller version of this query, we can narrow down old search results first
-    if query_text.contains(&last_query.text)
-      && (query.match_case == last_query.match_case || query.match_case == true)
-      && (query.only_outline == last_query.only_outline || query.only_outline == true)
-    {
-      println!("narrowed down old searches");
-      // loop through search_results.results and remove all that are not in query
-      // it is guaranteed that there will be less
-      let mut new_results = Vec::new();
-      for result in search_results.results.iter() {
-        // if the amount of matches in text is larger than query_index
-        let combined_text = search_results.para_texts[result.link].clone();
-        if combined_text.to_lowercase().matches(&query_text).count() > result.query_index
-          && (!query.only_outline || result.para.outline_level.is_some())
-        {
-          let mut new_result = result.clone();
-          new_result.index = new_results.len();
-          new_results.push(new_result);
-        }
-      }
-
-      search_results.results = new_results;
-    } else
-    // if last query is bigger version of this query, we can clear old search results
-    {
-      println!("cleared old searches");
-      search_results.results.clear();
-    }
-  } else {
-    println!("no old searches")
-  }
-  search_results.last_query = Some(query.clone());
-  // fill in the needed search_results.results
-  let last_result = search_results.results.last();
-  let mut l = 0;
-  if last_result.is_some() {
-    l = last_result.unwrap().para.index + 1;
-  }
-  while search_results.results.len() < j && l < paras.len() {
-    if !query.only_outline || paras[l].outline_level.is_some() {
-      let combined_text = &search_results.para_texts[l];
-      let combined_text = match query.match_case {
-        true => combined_text.clone(),
-        false => combined_text.to_lowercase(),
-      };
-      for k in 0..combined_text.matches(&query_text).count() {
-        let index = search_results.results.len();
-        search_results.results.push(SearchResult {
-          link: l.clone(),
-          index: index,
-          para: paras[l].clone(),
-          query_index: k,
-        });
-      }
-    }
-    l += 1;
-  }
-  let mut result = Vec::new();
   println!("requested search results: {:?}..{:?}", i, j);
-  if search_results.results.len() > 0 {
-    for l in i..j {
-      if (search_results.results.len() - 1) < l {
-        break;
-      }
-      result.push(search_results.results[l].clone());
-    }
-  }
+  let result = search_results
+    .search(&query, paras, i, j)
+    .map_err(|e| e.to_string())?;
   println!("response length: {:?}", result.len());
-  result
+  Ok(result)
+}
+#[tauri::command]
+pub fn find_matches(
+  query: Query,
+  link: usize,
+  paras: State<'_, Paras>,
+  search_results: State<'_, SearchResults>,
+  window: Window,
+) -> Result<Vec<Range<usize>>, String> {
+  let label = window.label();
+
+  let mut paras_dict = paras.0.lock().unwrap();
+  let paras = paras_dict.get_mut(label).unwrap();
+
+  let mut search_results_dict = search_results.0.lock().unwrap();
+  let search_results = search_results_dict.get_mut(label).unwrap();
+
+  search_results
+    .find(&query, paras, link)
+    .map_err(|e| e.to_string())
 }
 #[tauri::command]
 pub fn clear_search(search_results: State<SearchResults>, window: Window) -> bool {
   let label = window.label();
   println!("unloading search");
   let mut search_results_dict = search_results.0.lock().unwrap();
-  let mut search_results = search_results_dict.get_mut(label).unwrap();
-  search_results.results.clear();
-  search_results.last_query = None;
+  let search_results = search_results_dict.get_mut(label).unwrap();
+  search_results.clear();
   return true;
 }
 
@@ -319,3 +415,11 @@ pub fn get_nearest_outline_para(
   }
   result
 }
+#[tauri::command]
+pub fn get_document_info(
+  document_infos: State<DocumentInfos>,
+  window: Window,
+) -> Option<DocumentInfo> {
+  let document_infos = document_infos.0.lock().unwrap();
+  document_infos.get(window.label()).cloned()
+}
@@ -0,0 +1,268 @@
+use crate::document_commands::{DocumentInfos, OutlineParas, Paras};
+
+use docx_parser::{Clipboard, DocumentInfo, Export, Highlight, PaperSize};
+use std::fs;
+use std::path::Path;
+use tauri::State;
+use tauri::Window;
+
+// renders the window's document, or the section starting at paragraph
+// `section`, and writes it to `path`
+fn export_file(
+  path: &str,
+  section: Option<usize>,
+  paras: &State<Paras>,
+  outline_paras: &State<OutlineParas>,
+  document_infos: &State<DocumentInfos>,
+  window: &Window,
+  render: impl Fn(&Export, &DocumentInfo) -> Vec<u8>,
+) -> Result<(), String> {
+  let label = window.label();
+  let paras_dict = paras.0.lock().unwrap();
+  let outline_paras_dict = outline_paras.0.lock().unwrap();
+  let document_infos = document_infos.0.lock().unwrap();
+  let (paras, outline_paras, info) = match (
+    paras_dict.get(label),
+    outline_paras_dict.get(label),
+    document_infos.get(label),
+  ) {
+    (Some(paras), Some(outline_paras), Some(info)) => (paras, outline_paras, info),
+    _ => return Err("no document is open".to_string()),
+  };
+  // without a title in the properties, use the name it's saved as
+  let name = Path::new(path)
+    .file_stem()
+    .map(|stem| stem.to_string_lossy().into_owned())
+    .unwrap_or_default();
+  let export = Export::new(info.title_or(&name), paras, outline_paras);
+  let export = match section {
+    Some(section) => export
+      .section(section)
+      .ok_or_else(|| format!("paragraph {} doesn't exist", section))?,
+    None => export,
+  };
+  fs::write(path, render(&export, info)).map_err(|e| e.to_string())
+}
+#[tauri::command]
+pub fn export_html(
+  path: String,
+  section: Option<usize>,
+  paras: State<Paras>,
+  outline_paras: State<OutlineParas>,
+  document_infos: State<DocumentInfos>,
+  window: Window,
+) -> Result<(), String> {
+  println!(
+    "exporting {:?} as html in window {:?}",
+    path,
+    window.label()
+  );
+  export_file(
+    &path,
+    section,
+    &paras,
+    &outline_paras,
+    &document_infos,
+    &window,
+    |export, _| export.to_html().into_bytes(),
+  )
+}
+#[tauri::command]
+pub fn export_markdown(
+  path: String,
+  section: Option<usize>,
+  highlight: Highlight,
+  paras: State<Paras>,
+  outline_paras: State<OutlineParas>,
+  document_infos: State<DocumentInfos>,
+  window: Window,
+) -> Result<(), String> {
+  println!(
+    "exporting {:?} as markdown in window {:?}",
+    path,
+    window.label()
+  );
+  export_file(
+    &path,
+    section,
+    &paras,
+    &outline_paras,
+    &document_infos,
+    &window,
+    |export, _| export.to_markdown(highlight).into_bytes(),
+  )
+}
+// `paper` is a size name like "a4", letter if it's missing. `margin` is in
+// points on every side, the paper's own margins if it's missing
+#[tauri::command]
+pub fn export_pdf(
+  path: String,
+  section: Option<usize>,
+  paper: Option<PaperSize>,
+  margin: Option<f32>,
+  paras: State<Paras>,
+  outline_paras: State<OutlineParas>,
+  document_infos: State<DocumentInfos>,
+  window: Window,
+) -> Result<(), String> {
+  println!("exporting {:?} as pdf in window {:?}", path, window.label());
+  let page = paper.map(PaperSize::page_setup).unwrap_or_default();
+  let page = match margin {
+    Some(margin) => page
+      .with_margin(margin)
+      .ok_or_else(|| format!("{} point margins leave no room on the page", margin))?,
+    None => page,
+  };
+  export_file(
+    &path,
+    section,
+    &paras,
+    &outline_paras,
+    &document_infos,
+    &window,
+    |export, _| export.to_pdf(&page),
+  )
+}
+// `split_level` is the outline level that starts a new chapter
+#[tauri::command]
+pub fn export_epub(
+  path: String,
+  section: Option<usize>,
+  split_level: u32,
+  paras: State<Paras>,
+  outline_paras: State<OutlineParas>,
+  document_infos: State<DocumentInfos>,
+  window: Window,
+) -> Result<(), String> {
+  println!(
+    "exporting {:?} as epub in window {:?}",
+    path,
+    window.label()
+  );
+  export_file(
+    &path,
+    section,
+    &paras,
+    &outline_paras,
+    &document_infos,
+    &window,
+    |export, info| export.to_epub(info, split_level),
+  )
+}
+#[tauri::command]
+pub fn export_json(
+  path: String,
+  section: Option<usize>,
+  paras: State<Paras>,
+  outline_paras: State<OutlineParas>,
+  document_infos: State<DocumentInfos>,
+  window: Window,
+) -> Result<(), String> {
+  println!(
+    "exporting {:?} as json in window {:?}",
+    path,
+    window.label()
+  );
+  export_file(
+    &path,
+    section,
+    &paras,
+    &outline_paras,
+    &document_infos,
+    &window,
+    |export, info| export.to_json(info).into_bytes(),
+  )
+}
+// puts every flavor on the clipboard at once, replacing what was there
+#[cfg(target_os = "macos")]
+fn write_clipboard(clipboard: &Clipboard) -> Result<(), String> {
+  use cocoa::appkit::{
+    NSPasteboard, NSPasteboardTypeHTML, NSPasteboardTypeRTF, NSPasteboardTypeString,
+  };
+  use cocoa::base::{nil, YES};
+  use cocoa::foundation::{NSAutoreleasePool, NSData, NSString};
+  use std::ffi::c_void;
+  unsafe {
+    let pool = NSAutoreleasePool::new(nil);
+    let pasteboard = NSPasteboard::generalPasteboard(nil);
+    pasteboard.clearContents();
+    let rtf = NSData::dataWithBytes_length_(
+      nil,
+      clipboard.rtf.as_ptr() as *const c_void,
+      clipboard.rtf.len() as _,
+    );
+    let html = NSString::alloc(nil).init_str(&clipboard.html).autorelease();
+    let text = NSString::alloc(nil).init_str(&clipboard.text).autorelease();
+    let written = pasteboard.setData_forType(rtf, NSPasteboardTypeRTF) == YES
+      && pasteboard.setString_forType(html, NSPasteboardTypeHTML) == YES
+      && pasteboard.setString_forType(text, NSPasteboardTypeString) == YES;
+    pool.drain();
+    if written {
+      Ok(())
+    } else {
+      Err("couldn't write to the clipboard".to_string())
+    }
+  }
+}
+#[cfg(target_os = "windows")]
+fn write_clipboard(clipboard: &Clipboard) -> Result<(), String> {
+  use clipboard_win::{raw, Clipboard as OpenClipboard};
+  let failed = |e| format!("couldn't write to the clipboard: {}", e);
+  let register = |name| {
+    raw::register_format(name)
+      .ok_or_else(|| format!("couldn't register the {:?} clipboard format", name))
+  };
+  let rtf = register("Rich Text Format")?;
+  let html = register("HTML Format")?;
+  let _open = OpenClipboard::new_attempts(10).map_err(failed)?;
+  // setting the text empties the clipboard first
+  raw::set_string(&clipboard.text).map_err(failed)?;
+  raw::set_without_clear(rtf.get(), clipboard.rtf.as_bytes()).map_err(failed)?;
+  raw::set_without_clear(html.get(), clipboard.windows_html().as_bytes()).map_err(failed)
+}
+// x11 and wayland ask the app that copied for the contents on every paste, so
+// the clipboard lives as long as the app. arboard can't offer text/rtf, so
+// only html keeps the formatting here, which LibreOffice pastes too
+#[cfg(target_os = "linux")]
+fn write_clipboard(clipboard: &Clipboard) -> Result<(), String> {
+  use once_cell::sync::Lazy;
+  use std::sync::Mutex;
+  static CLIPBOARD: Lazy<Mutex<Option<arboard::Clipboard>>> = Lazy::new(Default::default);
+  let failed = |e| format!("couldn't write to the clipboard: {}", e);
+  let mut owner = CLIPBOARD.lock().unwrap();
+  if owner.is_none() {
+    *owner = Some(arboard::Clipboard::new().map_err(failed)?);
+  }
+  owner
+    .as_mut()
+    .unwrap()
+    .set_html(&clipboard.html, Some(&clipboard.text))
+    .map_err(failed)
+}
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+fn write_clipboard(_clipboard: &Clipboard) -> Result<(), String> {
+  Err("copying with formatting isn't supported on this platform".to_string())
+}
+// copies the window's document from `start` to `end`, each a paragraph and a
+// character offset in it, with its formatting so Word keeps the emphasis
+#[tauri::command]
+pub fn copy_range(
+  start: (usize, usize),
+  end: (usize, usize),
+  paras: State<Paras>,
+  outline_paras: State<OutlineParas>,
+  window: Window,
+) -> Result<(), String> {
+  let label = window.label();
+  println!("copying {:?}..{:?} in window {:?}", start, end, label);
+  let paras_dict = paras.0.lock().unwrap();
+  let outline_paras_dict = outline_paras.0.lock().unwrap();
+  let (paras, outline_paras) = match (paras_dict.get(label), outline_paras_dict.get(label)) {
+    (Some(paras), Some(outline_paras)) => (paras, outline_paras),
+    _ => return Err("no document is open".to_string()),
+  };
+  let clipboard = Export::new("", paras, outline_paras)
+    .clipboard(start, end)
+    .ok_or_else(|| format!("{:?}..{:?} isn't in the document", start, end))?;
+  write_clipboard(&clipboard)
+}
@@ -7,18 +7,25 @@ use cocoa::appkit::{NSWindow, NSWindowStyleMask};
 use std::collections::HashMap;
 use std::path::PathBuf;
 use tauri::api::dialog::FileDialogBuilder;
-use tauri::{AppHandle, Runtime, State, Window, WindowBuilder, WindowUrl};
+use tauri::{AppHandle, Runtime, State, Window, WindowBuilder, WindowEvent, WindowUrl};
 
-mod document;
+mod cache;
 
 mod app_commands;
 mod document_commands;
+mod export_commands;
 mod menu;
+mod speech_commands;
+mod watch;
 
-use app_commands::{WindowCreate, WindowsCreate, WindowsCreateState};
-use document_commands::{OutlineParas, Paras, SearchResults, SearchResultsState};
+use app_commands::{WindowCreate, WindowsCreate, WindowsCreateState, DOCUMENT_EXTENSIONS};
+use cache::{Cache, DocumentCache, MAX_CACHE_SIZE};
+use document_commands::{DocumentInfos, OpenFiles, OutlineParas, Paras, SearchResults};
+use docx_parser::Search;
 
 use menu::get_menu;
+use speech_commands::Speech;
+use watch::{unwatch_file, FileWatchers};
 
 use std::sync::Mutex;
 
@@ -86,14 +93,7 @@ fn new_window(
 
         paras_dict.insert(label.clone(), Vec::new());
         outline_paras_dict.insert(label.clone(), Vec::new());
-        search_results_dict.insert(
-            label.clone(),
-            SearchResultsState {
-                results: Vec::new(),
-                last_query: None,
-                para_texts: Vec::new(),
-            },
-        );
+        search_results_dict.insert(label.clone(), Search::new());
 
         pos.x += 80;
         pos.y += 80;
@@ -142,16 +142,37 @@ fn main() {
             {
                 win.set_transparent_titlebar(true);
             }
+            let cache_dir = app
+                .path_resolver()
+                .app_dir()
+                .map(|dir| dir.join("parse_cache"));
+            app.manage(DocumentCache(Mutex::new(Cache::new(
+                cache_dir,
+                MAX_CACHE_SIZE,
+            ))));
             app.listen_global("tauri://focus", |event| {
                 println!("{:?}", event.payload());
             });
 
             Ok(())
         })
+        .on_window_event(|event| {
+            if let WindowEvent::Destroyed = event.event() {
+                let window = event.window();
+                // stop watching, and drop the path so a reload already underway is dropped too
+                unwatch_file(window.label(), &window.state::<FileWatchers>());
+                window
+                    .state::<OpenFiles>()
+                    .0
+                    .lock()
+                    .unwrap()
+                    .remove(window.label());
+            }
+        })
         .menu(get_menu())
         .on_menu_event(|event| match event.menu_item_id() {
             "open" => FileDialogBuilder::new()
-                .add_filter("Word Document", &["docx"])
+                .add_filter("Document", DOCUMENT_EXTENSIONS)
                 .pick_files(move |paths| {
                     if paths.is_some() {
                         println!("opening file in window: {:?}", event.window().label());
@@ -161,6 +182,20 @@ fn main() {
                             .unwrap()
                     }
                 }),
+            "export" => event
+                .window()
+                .emit_to(event.window().label(), "export", ())
+                .unwrap(),
+            "clear cache" => {
+                println!("clearing document cache");
+                event
+                    .window()
+                    .state::<DocumentCache>()
+                    .0
+                    .lock()
+                    .unwrap()
+                    .clear();
+            }
             "learn more" => {
                 match open(
                     &(event.window()).shell_scope(),
@@ -183,6 +218,10 @@ fn main() {
             "main".to_string(),
             Vec::new(),
         )]))))
+        .manage(DocumentInfos(Mutex::new(HashMap::new())))
+        .manage(OpenFiles(Mutex::new(HashMap::new())))
+        .manage(Speech(Mutex::new(Vec::new())))
+        .manage(FileWatchers(Mutex::new(HashMap::new())))
         .manage(WindowsCreate(Mutex::new(WindowsCreateState {
             label_counter: 0,
             last_focus: "main".into(),
@@ -190,20 +229,32 @@ fn main() {
         })))
         .manage(SearchResults(Mutex::new(HashMap::from([(
             "main".to_string(),
-            SearchResultsState {
-                results: Vec::new(),
-                last_query: None,
-                para_texts: Vec::new(),
-            },
+            Search::new(),
         )]))))
         .invoke_handler(tauri::generate_handler![
             document_commands::load_file,
+            document_commands::load_encrypted_file,
             document_commands::get_paras,
             document_commands::search,
             document_commands::clear_search,
+            document_commands::find_matches,
             document_commands::unload_file,
             document_commands::get_outline_paras,
             document_commands::get_nearest_outline_para,
+            document_commands::get_document_info,
+            export_commands::export_html,
+            export_commands::export_markdown,
+            export_commands::export_pdf,
+            export_commands::export_epub,
+            export_commands::export_json,
+            export_commands::copy_range,
+            speech_commands::speech_add,
+            speech_commands::speech_remove,
+            speech_commands::speech_move,
+            speech_commands::speech_clear,
+            speech_commands::get_speech,
+            speech_commands::save_speech,
+            speech_commands::export_sections_docx,
             app_commands::open_dialog,
             app_commands::get_window_fullscreen_state,
             app_commands::window_ready,
@@ -28,6 +28,13 @@ pub fn get_menu() -> Menu {
       Menu::new()
         .add_native_item(MenuItem::CloseWindow)
         .add_item(CustomMenuItem::new("open".to_string(), "Open"))
+        .add_item(CustomMenuItem::new("export".to_string(), "Export"))
+        .add_native_item(MenuItem::Separator)
+        // parsed documents are cached on disk, this frees the space
+        .add_item(CustomMenuItem::new(
+          "clear cache".to_string(),
+          "Clear Cache",
+        ))
         .add_native_item(MenuItem::Quit),
     ))
     .add_submenu(Submenu::new("Edit", {
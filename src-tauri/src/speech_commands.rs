@@ -0,0 +1,218 @@
+use crate::document_commands::{OpenFiles, OutlineParas, Paras};
+
+use docx_parser::{DocxWriter, Export};
+use serde::Serialize;
+use std::fs;
+use std::ops::RangeInclusive;
+use std::sync::{Arc, Mutex};
+use tauri::{Manager, State, Window};
+
+// sections picked from any window's document, to save together as a new docx
+pub struct Speech(pub Mutex<Vec<SpeechSection>>);
+
+// the file a section was copied out of, as it was when it was added, so
+// editing or closing the file later doesn't change the speech
+pub struct SpeechSource {
+  path: String,
+  bytes: Arc<Vec<u8>>,
+}
+
+pub struct SpeechSection {
+  source: Arc<SpeechSource>,
+  paras: RangeInclusive<usize>,
+  info: SectionInfo,
+}
+
+#[derive(Clone, Serialize)]
+pub struct SectionInfo {
+  pub title: String,
+  pub path: String,
+  pub outline_level: Option<u32>,
+  pub para_count: usize,
+}
+
+fn section_infos(sections: &[SpeechSection]) -> Vec<SectionInfo> {
+  sections
+    .iter()
+    .map(|section| section.info.clone())
+    .collect()
+}
+// every window shows the same speech
+fn speech_changed(window: &Window, sections: &[SpeechSection]) -> Vec<SectionInfo> {
+  let infos = section_infos(sections);
+  window.emit_all("speech_changed", infos.clone()).unwrap();
+  infos
+}
+// adds the heading at paragraph `link`, with everything under it, from the
+// document open in window `label`, or the calling window
+#[tauri::command]
+pub fn speech_add(
+  link: usize,
+  label: Option<String>,
+  speech: State<Speech>,
+  paras: State<Paras>,
+  outline_paras: State<OutlineParas>,
+  open_files: State<OpenFiles>,
+  window: Window,
+) -> Result<Vec<SectionInfo>, String> {
+  let label = label.unwrap_or_else(|| window.label().to_string());
+  println!("adding section {:?} of window {:?} to speech", link, label);
+  let (path, bytes, title, outline_level, para_range) = {
+    // paragraphs and bytes are locked together, so a reload can't come between them
+    let paras_dict = paras.0.lock().unwrap();
+    let outline_paras_dict = outline_paras.0.lock().unwrap();
+    let open_files = open_files.0.lock().unwrap();
+    let (paras, outline_paras, open_file) = match (
+      paras_dict.get(&label),
+      outline_paras_dict.get(&label),
+      open_files.get(&label),
+    ) {
+      (Some(paras), Some(outline_paras), Some(open_file)) => (paras, outline_paras, open_file),
+      _ => return Err("no document is open".to_string()),
+    };
+    let section = Export::new("", paras, outline_paras)
+      .section_at(link)
+      .ok_or_else(|| format!("paragraph {} doesn't exist", link))?;
+    (
+      open_file.path.clone(),
+      open_file.bytes.clone(),
+      section.title.clone(),
+      section.paras[0].outline_level,
+      section.para_range(),
+    )
+  };
+  let mut sections = speech.0.lock().unwrap();
+  // sections from the same version of a file share it
+  let existing = sections
+    .iter()
+    .find(|section| section.source.path == path && section.source.bytes == bytes)
+    .map(|section| section.source.clone());
+  let source = match existing {
+    Some(source) => source,
+    None => {
+      // only docx files can be copied from
+      DocxWriter::new(&bytes).map_err(|e| e.to_string())?;
+      Arc::new(SpeechSource {
+        path: path.clone(),
+        bytes,
+      })
+    }
+  };
+  sections.push(SpeechSection {
+    source,
+    info: SectionInfo {
+      title,
+      path,
+      outline_level,
+      para_count: para_range.end() - para_range.start() + 1,
+    },
+    paras: para_range,
+  });
+  Ok(speech_changed(&window, &sections))
+}
+#[tauri::command]
+pub fn speech_remove(
+  index: usize,
+  speech: State<Speech>,
+  window: Window,
+) -> Result<Vec<SectionInfo>, String> {
+  let mut sections = speech.0.lock().unwrap();
+  if index >= sections.len() {
+    return Err(format!("section {} doesn't exist", index));
+  }
+  sections.remove(index);
+  Ok(speech_changed(&window, &sections))
+}
+#[tauri::command]
+pub fn speech_move(
+  from: usize,
+  to: usize,
+  speech: State<Speech>,
+  window: Window,
+) -> Result<Vec<SectionInfo>, String> {
+  let mut sections = speech.0.lock().unwrap();
+  if from >= sections.len() || to >= sections.len() {
+    return Err(format!("section {} doesn't exist", from.max(to)));
+  }
+  let section = sections.remove(from);
+  sections.insert(to, section);
+  Ok(speech_changed(&window, &sections))
+}
+#[tauri::command]
+pub fn speech_clear(speech: State<Speech>, window: Window) -> Vec<SectionInfo> {
+  let mut sections = speech.0.lock().unwrap();
+  sections.clear();
+  speech_changed(&window, &sections)
+}
+#[tauri::command]
+pub fn get_speech(speech: State<Speech>) -> Vec<SectionInfo> {
+  section_infos(&speech.0.lock().unwrap())
+}
+// writes the sections in order to a new docx at `path`, using the first
+// section's file for the styles and page setup
+#[tauri::command]
+pub fn save_speech(path: String, speech: State<Speech>) -> Result<(), String> {
+  let sections = speech.0.lock().unwrap();
+  println!("saving speech of {} sections to {:?}", sections.len(), path);
+  let first = match sections.first() {
+    Some(first) => first,
+    None => return Err("the speech is empty".to_string()),
+  };
+  let mut writer = DocxWriter::new(&first.source.bytes).map_err(|e| e.to_string())?;
+  let mut sources = vec![&first.source];
+  for section in sections.iter() {
+    let number = match sources
+      .iter()
+      .position(|source| Arc::ptr_eq(source, &section.source))
+    {
+      Some(number) => number,
+      None => {
+        sources.push(&section.source);
+        writer
+          .add_source(&section.source.bytes)
+          .map_err(|e| e.to_string())?
+      }
+    };
+    writer
+      .copy(number, section.paras.clone())
+      .map_err(|e| e.to_string())?;
+  }
+  let docx = writer.finish().map_err(|e| e.to_string())?;
+  fs::write(&path, docx).map_err(|e| e.to_string())
+}
+// copies the headings at `links`, with everything under them, out of the
+// window's docx into a new docx at `path`, without going through the speech
+#[tauri::command]
+pub fn export_sections_docx(
+  path: String,
+  links: Vec<usize>,
+  paras: State<Paras>,
+  outline_paras: State<OutlineParas>,
+  open_files: State<OpenFiles>,
+  window: Window,
+) -> Result<(), String> {
+  let label = window.label();
+  println!(
+    "copying {} sections to {:?} in window {:?}",
+    links.len(),
+    path,
+    label
+  );
+  let docx = {
+    let paras_dict = paras.0.lock().unwrap();
+    let outline_paras_dict = outline_paras.0.lock().unwrap();
+    let open_files = open_files.0.lock().unwrap();
+    let (paras, outline_paras, open_file) = match (
+      paras_dict.get(label),
+      outline_paras_dict.get(label),
+      open_files.get(label),
+    ) {
+      (Some(paras), Some(outline_paras), Some(open_file)) => (paras, outline_paras, open_file),
+      _ => return Err("no document is open".to_string()),
+    };
+    Export::new("", paras, outline_paras)
+      .sections_to_docx(&open_file.bytes, &links)
+      .map_err(|e| e.to_string())?
+  };
+  fs::write(&path, docx).map_err(|e| e.to_string())
+}
@@ -0,0 +1,290 @@
+use crate::cache::DocumentCache;
+use crate::document_commands::{
+  parse_bytes, set_document, DocumentInfos, OpenFiles, OutlineParas, Paras, SearchResults,
+};
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tauri::{Manager, Window};
+
+// editors write files in several steps, so wait until events stop before reloading
+const DEBOUNCE: Duration = Duration::from_millis(400);
+
+pub struct FileWatchers(pub Mutex<HashMap<String, RecommendedWatcher>>);
+
+#[derive(Clone, Serialize)]
+pub struct DocumentChanged {
+  pub path: String,
+  // para_map[old_index] is the index of the same paragraph in the new document,
+  // or None if it was edited or removed
+  pub para_map: Vec<Option<usize>>,
+}
+
+pub fn watch_file(window: &Window, path: &str, watchers: &FileWatchers) {
+  let label = window.label().to_string();
+  let mut watchers = watchers.0.lock().unwrap();
+  // dropping the old watcher also ends its reload thread
+  watchers.remove(&label);
+
+  let file_path = PathBuf::from(path);
+  let file_name = match file_path.file_name() {
+    Some(file_name) => file_name.to_owned(),
+    None => return,
+  };
+  // watch the parent directory, because saving usually replaces the file
+  // instead of writing to it, which would end a watch on the file itself
+  let dir = match file_path.parent() {
+    Some(dir) if dir != Path::new("") => dir.to_path_buf(),
+    _ => PathBuf::from("."),
+  };
+  let (sender, receiver) = channel();
+  let mut watcher = match notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+    if let Ok(event) = res {
+      let relevant = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+        && event
+          .paths
+          .iter()
+          .any(|event_path| event_path.file_name() == Some(&file_name));
+      if relevant {
+        sender.send(()).ok();
+      }
+    }
+  }) {
+    Ok(watcher) => watcher,
+    Err(e) => {
+      println!("couldn't create watcher for {:?}: {}", path, e);
+      return;
+    }
+  };
+  if let Err(e) = watcher.watch(&dir, RecursiveMode::NonRecursive) {
+    println!("couldn't watch {:?}: {}", dir, e);
+    return;
+  }
+  println!("watching {:?} in window {:?}", path, label);
+
+  let window = window.clone();
+  let path = path.to_string();
+  thread::spawn(move || {
+    // recv fails once the watcher (and its sender) is dropped
+    while receiver.recv().is_ok() {
+      loop {
+        match receiver.recv_timeout(DEBOUNCE) {
+          Ok(()) => continue,
+          Err(RecvTimeoutError::Timeout) => break,
+          Err(RecvTimeoutError::Disconnected) => return,
+        }
+      }
+      reload_file(&window, &path);
+    }
+  });
+  watchers.insert(label, watcher);
+}
+
+pub fn unwatch_file(label: &str, watchers: &FileWatchers) {
+  watchers.0.lock().unwrap().remove(label);
+}
+
+fn reload_file(window: &Window, path: &str) {
+  let label = window.label();
+  let bytes = match fs::read(path) {
+    Ok(bytes) => bytes,
+    Err(e) => {
+      println!("couldn't reread {:?}: {}", path, e);
+      return;
+    }
+  };
+  let cache = window.state::<DocumentCache>();
+  // half written files come back as errors like any other bad input. catching
+  // panics is only a last resort, so a parser bug doesn't take down the
+  // watcher thread
+  let doc = match panic::catch_unwind(panic::AssertUnwindSafe(|| {
+    parse_bytes(&bytes, None, &cache)
+  })) {
+    Ok(Ok(doc)) => doc,
+    Ok(Err(e)) => {
+      println!("couldn't reload {:?}: {}", path, e);
+      return;
+    }
+    Err(_) => {
+      println!("parser panicked on {:?}, this is a bug", path);
+      return;
+    }
+  };
+
+  let paras = window.state::<Paras>();
+  let outline_paras = window.state::<OutlineParas>();
+  let search_results = window.state::<SearchResults>();
+  let open_files = window.state::<OpenFiles>();
+  let mut paras_dict = paras.0.lock().unwrap();
+  let mut outline_paras_dict = outline_paras.0.lock().unwrap();
+  let mut search_results_dict = search_results.0.lock().unwrap();
+  // the window can have opened another file or closed while this one parsed
+  let mut open_files = open_files.0.lock().unwrap();
+  let open_file = match open_files.get_mut(label) {
+    Some(open_file) if open_file.path == path => open_file,
+    _ => {
+      println!(
+        "window {:?} no longer shows {:?}, dropping reload",
+        label, path
+      );
+      return;
+    }
+  };
+  let (paras, outline_paras, search_results) = match (
+    paras_dict.get_mut(label),
+    outline_paras_dict.get_mut(label),
+    search_results_dict.get_mut(label),
+  ) {
+    (Some(paras), Some(outline_paras), Some(search_results)) => {
+      (paras, outline_paras, search_results)
+    }
+    _ => return,
+  };
+  window
+    .state::<DocumentInfos>()
+    .0
+    .lock()
+    .unwrap()
+    .insert(label.to_string(), doc.info.clone());
+  open_file.bytes = Arc::new(bytes);
+  let old_texts = std::mem::take(&mut search_results.para_texts);
+  set_document(doc, paras, outline_paras, search_results);
+  let para_map = map_paras(&old_texts, &search_results.para_texts);
+  println!("reloaded {:?} in window {:?}", path, label);
+  drop(paras_dict);
+  drop(outline_paras_dict);
+  drop(search_results_dict);
+
+  // keep the path locked until the event is out, so it can't reach a file
+  // opened in the meantime. the window can be closing, which fails the emit,
+  // and panicking here would poison the lock for every other window
+  if let Err(e) = window.emit(
+    "document_changed",
+    DocumentChanged {
+      path: path.to_string(),
+      para_map,
+    },
+  ) {
+    println!("couldn't tell window {:?} about the reload: {}", label, e);
+  }
+  drop(open_files);
+}
+
+// match paragraphs of the old document to the new one by their text,
+// keeping the order so an insertion doesn't shuffle everything after it
+pub fn map_paras(old: &[String], new: &[String]) -> Vec<Option<usize>> {
+  let mut para_map = vec![None; old.len()];
+  // unchanged start and end are by far the most common case
+  let mut prefix = 0;
+  while prefix < old.len() && prefix < new.len() && old[prefix] == new[prefix] {
+    para_map[prefix] = Some(prefix);
+    prefix += 1;
+  }
+  let mut suffix = 0;
+  while suffix < old.len() - prefix
+    && suffix < new.len() - prefix
+    && old[old.len() - 1 - suffix] == new[new.len() - 1 - suffix]
+  {
+    para_map[old.len() - 1 - suffix] = Some(new.len() - 1 - suffix);
+    suffix += 1;
+  }
+  // for the middle, greedily take the next unused new para with the same text
+  let mut positions: HashMap<&str, VecDeque<usize>> = HashMap::new();
+  for (i, text) in new.iter().enumerate().take(new.len() - suffix).skip(prefix) {
+    positions.entry(text).or_default().push_back(i);
+  }
+  let mut next = prefix;
+  for (i, text) in old.iter().enumerate().take(old.len() - suffix).skip(prefix) {
+    if let Some(queue) = positions.get_mut(text.as_str()) {
+      while queue.front().map_or(false, |&j| j < next) {
+        queue.pop_front();
+      }
+      if let Some(j) = queue.pop_front() {
+        para_map[i] = Some(j);
+        next = j + 1;
+      }
+    }
+  }
+  para_map
+}
+
+#[cfg(test)]
+mod tests {
+  use super::map_paras;
+
+  fn texts(texts: &[&str]) -> Vec<String> {
+    texts.iter().map(|text| text.to_string()).collect()
+  }
+
+  #[test]
+  fn unchanged() {
+    let old = texts(&["a", "b", "c"]);
+    assert_eq!(map_paras(&old, &old), vec![Some(0), Some(1), Some(2)]);
+  }
+
+  #[test]
+  fn insert() {
+    let old = texts(&["a", "b", "c", "d"]);
+    let new = texts(&["a", "b", "new", "c", "d"]);
+    assert_eq!(
+      map_paras(&old, &new),
+      vec![Some(0), Some(1), Some(3), Some(4)]
+    );
+    let new = texts(&["new", "a", "b", "c", "d"]);
+    assert_eq!(
+      map_paras(&old, &new),
+      vec![Some(1), Some(2), Some(3), Some(4)]
+    );
+    let new = texts(&["a", "b", "c", "d", "new"]);
+    assert_eq!(
+      map_paras(&old, &new),
+      vec![Some(0), Some(1), Some(2), Some(3)]
+    );
+  }
+
+  #[test]
+  fn delete() {
+    let old = texts(&["a", "b", "c", "d"]);
+    let new = texts(&["a", "c", "d"]);
+    assert_eq!(map_paras(&old, &new), vec![Some(0), None, Some(1), Some(2)]);
+    let new = texts(&["b", "c", "d"]);
+    assert_eq!(map_paras(&old, &new), vec![None, Some(0), Some(1), Some(2)]);
+    assert_eq!(map_paras(&old, &[]), vec![None; 4]);
+  }
+
+  #[test]
+  fn edit() {
+    let old = texts(&["a", "b", "c", "d"]);
+    let new = texts(&["a", "b edited", "c", "d"]);
+    assert_eq!(map_paras(&old, &new), vec![Some(0), None, Some(2), Some(3)]);
+  }
+
+  #[test]
+  fn edits_in_the_middle_keep_order() {
+    // the moved "c" can't map back before "b"'s new position
+    let old = texts(&["a", "c", "b", "x", "z"]);
+    let new = texts(&["a", "b", "y", "c", "z"]);
+    assert_eq!(
+      map_paras(&old, &new),
+      vec![Some(0), Some(3), None, None, Some(4)]
+    );
+  }
+
+  #[test]
+  fn repeated_text() {
+    let old = texts(&["x", "", "a", "", "y"]);
+    let new = texts(&["z", "", "", "a", "", "w"]);
+    assert_eq!(
+      map_paras(&old, &new),
+      vec![None, Some(1), Some(3), Some(4), None]
+    );
+  }
+}
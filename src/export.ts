@@ -0,0 +1,123 @@
+import { invoke } from '@tauri-apps/api';
+import { save } from '@tauri-apps/api/dialog';
+
+// letter paper in the US and Canada, A4 everywhere else
+const region = navigator.language.split('-')[1]?.toUpperCase();
+const defaultPage = region == 'US' || region == 'CA' ? 'letter' : 'a4';
+
+// points in each unit margins can be given in
+const points: Record<string, number> = {
+  in: 72,
+  cm: 72 / 2.54,
+  mm: 72 / 25.4,
+  pt: 1,
+};
+
+// asks for the paper and, optionally, the margins on every side, like
+// "a4 2cm", remembering the answer for next time. without margins the
+// paper's own are used
+function askPageSetup() {
+  let answer = localStorage.getItem('pdfPage') ?? defaultPage;
+  let message = 'Paper (letter or a4) and margins (in, cm, mm or pt)';
+  while (true) {
+    answer = prompt(message, answer);
+    if (answer == null) return null;
+    let match = answer
+      .trim()
+      .toLowerCase()
+      .match(/^(letter|a4)(?:\s+(\d+(?:\.\d+)?)\s*(in|cm|mm|pt))?$/);
+    if (match) {
+      localStorage.setItem('pdfPage', answer.trim());
+      let [, paper, margin, unit] = match;
+      return {
+        paper,
+        margin: margin ? parseFloat(margin) * points[unit] : null,
+      };
+    }
+    message = 'Try something like "letter 1in" or "a4 2.5cm"';
+  }
+}
+
+// asks which headings start a chapter, counted from 1 like Word's Heading 1,
+// remembering the answer for next time. hats (level 2) by default, pockets are
+// too long to page through
+function askSplitLevel() {
+  let answer = prompt(
+    'Start a chapter at each heading of level (1 to 9)',
+    localStorage.getItem('epubSplitLevel') ?? '2'
+  );
+  if (answer == null) return null;
+  let level = Math.min(Math.max(parseInt(answer) || 2, 1), 9);
+  localStorage.setItem('epubSplitLevel', `${level}`);
+  // outline levels start at 0
+  return { splitLevel: level - 1 };
+}
+
+// each format is written by its own command, picked by the saved extension.
+// args can be asked for after picking where to save, null cancels
+const formats: {
+  name: string;
+  extension: string;
+  command: string;
+  args?: object | (() => object | null);
+}[] = [
+  { name: 'Web Page', extension: 'html', command: 'export_html' },
+  {
+    name: 'Markdown',
+    extension: 'md',
+    command: 'export_markdown',
+    // <mark> shows up on GitHub, ==mark== only in some editors
+    args: { highlight: 'html' },
+  },
+  { name: 'PDF', extension: 'pdf', command: 'export_pdf', args: askPageSetup },
+  {
+    name: 'EPUB',
+    extension: 'epub',
+    command: 'export_epub',
+    args: askSplitLevel,
+  },
+  // for other tools, see src-tauri/docx-parser/schema
+  { name: 'JSON', extension: 'json', command: 'export_json' },
+];
+
+// asks where to save, then exports the whole document, or the section that
+// starts at paragraph `section`
+export async function exportDocument(section: number | null, name: string) {
+  // headings can have slashes in them, which would be read as folders
+  name = name.replace(/[\/\\:]/g, ' ').trim() || 'Untitled';
+  let path = await save({
+    defaultPath: `${name}.${formats[0].extension}`,
+    filters: formats.map((format) => ({
+      name: format.name,
+      extensions: [format.extension],
+    })),
+  });
+  if (!path) return;
+  let extension = path.split('.').pop().toLowerCase();
+  let format =
+    formats.find((format) => format.extension == extension) ?? formats[0];
+  let args = typeof format.args == 'function' ? format.args() : format.args;
+  if (args === null) return;
+  try {
+    await invoke(format.command, { path, section, ...args });
+  } catch (error) {
+    alert(`Couldn't export ${path.split('/').pop()}:\n${error}`);
+  }
+}
+
+// asks where to save, then copies the sections under the headings at `links`
+// out of this window's docx, looking the same as they do in Word
+export async function exportSections(links: number[], name: string) {
+  let path = await save({
+    defaultPath: `${name}.docx`,
+    filters: [{ name: 'Word Document', extensions: ['docx'] }],
+  });
+  if (!path) return false;
+  try {
+    await invoke('export_sections_docx', { path, links });
+    return true;
+  } catch (error) {
+    alert(`Couldn't export ${path.split('/').pop()}:\n${error}`);
+    return false;
+  }
+}
@@ -0,0 +1,38 @@
+import { invoke } from '@tauri-apps/api';
+import type { Query } from './types';
+
+// the query as the backend takes it
+export function backendQuery(query: Query) {
+  return {
+    text: query.text,
+    match_case: query.matchCase,
+    only_outline: query.onlyOutline,
+    regex: query.regex,
+    whole_word: query.wholeWord,
+    ignore_diacritics: query.ignoreDiacritics,
+  };
+}
+
+// the matches of the query in a paragraph, as [start, end) offsets in the
+// webview's utf-16 units. the backend finds them, so they're always the same as
+// the search results, folding and all
+export async function findMatches(text: string, link: number, query: Query) {
+  let matches: [number, number][] = [];
+  if (query.text.length == 0) return matches;
+  let ranges: { start: number; end: number }[];
+  try {
+    ranges = await invoke('find_matches', { query: backendQuery(query), link });
+  } catch {
+    // the search results say what's wrong with the query
+    return matches;
+  }
+  for (let { start, end } of ranges) {
+    matches.push([toUtf16Offset(text, start), toUtf16Offset(text, end)]);
+  }
+  return matches;
+}
+
+// a character offset from the backend as an offset in utf-16 units
+export function toUtf16Offset(text: string, offset: number) {
+  return [...text].slice(0, offset).join('').length;
+}
@@ -1,5 +1,8 @@
+import { invoke } from '@tauri-apps/api';
 import type { ParaType, RunType } from './types';
-export function getSelectionNode(parasElement: HTMLElement, items: ParaType[]) {
+// where the selection starts and ends, as positions in items, runs and the
+// text of a run
+function getSelectionPos(parasElement: HTMLElement, items: ParaType[]) {
   const selection = window.getSelection();
   const itemsElement = parasElement.querySelector('.items');
   if (!(selection.rangeCount && selection.containsNode(parasElement, true)))
@@ -86,6 +89,11 @@ export function getSelectionNode(parasElement: HTMLElement, items: ParaType[]) {
       items[selectPos.end.para].runs[selectPos.end.run].text.length;
   }
   console.log(selectPos);
+  return selectPos;
+}
+export function getSelectionNode(parasElement: HTMLElement, items: ParaType[]) {
+  let selectPos = getSelectionPos(parasElement, items);
+  if (!selectPos) return undefined;
   let ret = document.createElement('div');
   for (let i = selectPos.start.para; i < selectPos.end.para + 1; i++) {
     let para = items[i];
@@ -122,6 +130,27 @@ export function getSelectionNode(parasElement: HTMLElement, items: ParaType[]) {
   return ret;
 }
 
+// the selection as [paragraph index, character offset] pairs, which is how
+// copy_range takes it
+export function getSelectionRange(
+  parasElement: HTMLElement,
+  items: ParaType[]
+) {
+  let selectPos = getSelectionPos(parasElement, items);
+  if (!selectPos) return undefined;
+  // offsets in the webview count utf-16 units, the backend counts characters
+  function toRange(pos: { para: number; run: number; offset: number }) {
+    let para = items[pos.para];
+    let offset = 0;
+    for (let i = 0; i < pos.run; i++) {
+      offset += [...para.runs[i].text].length;
+    }
+    offset += [...(para.runs[pos.run]?.text ?? '').slice(0, pos.offset)].length;
+    return [para.index, offset];
+  }
+  return { start: toRange(selectPos.start), end: toRange(selectPos.end) };
+}
+
 export function getParaHTML(
   para: ParaType,
   runStart?: number,
@@ -198,3 +227,17 @@ export function copyToClipboard(html: HTMLElement) {
   });
   navigator.clipboard.write([clipboardItem]);
 }
+// copies with formatting from the backend, which also puts rtf on the
+// clipboard for Word, or just html and text where it can't
+export async function copyRange(
+  start: number[],
+  end: number[],
+  fallback: () => HTMLElement
+) {
+  try {
+    await invoke('copy_range', { start, end });
+  } catch (error) {
+    console.log("couldn't copy with rtf:", error);
+    copyToClipboard(fallback());
+  }
+}
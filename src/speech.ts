@@ -0,0 +1,50 @@
+import { invoke } from '@tauri-apps/api';
+import { listen } from '@tauri-apps/api/event';
+import { save } from '@tauri-apps/api/dialog';
+import { writable } from 'svelte/store';
+import type { SpeechSection } from './types';
+
+// sections picked from any window, kept by the backend so every window
+// shows the same speech
+export const speech = writable<SpeechSection[]>([]);
+invoke('get_speech').then((sections: SpeechSection[]) => speech.set(sections));
+listen('speech_changed', (event: { payload: SpeechSection[] }) => {
+  speech.set(event.payload);
+});
+
+async function change(command: string, args: object) {
+  try {
+    await invoke(command, args);
+  } catch (error) {
+    alert(`Couldn't change the speech:\n${error}`);
+  }
+}
+// adds the heading at paragraph `link` of this window's document, with
+// everything under it
+export function addSection(link: number) {
+  return change('speech_add', { link });
+}
+export function removeSection(index: number) {
+  return change('speech_remove', { index });
+}
+export function moveSection(from: number, to: number) {
+  return change('speech_move', { from, to });
+}
+export function clearSpeech() {
+  return change('speech_clear', {});
+}
+
+// asks where to save, then writes the speech as a docx that looks the same
+// in Word as the files it came from
+export async function saveSpeech() {
+  let path = await save({
+    defaultPath: 'Speech.docx',
+    filters: [{ name: 'Word Document', extensions: ['docx'] }],
+  });
+  if (!path) return;
+  try {
+    await invoke('save_speech', { path });
+  } catch (error) {
+    alert(`Couldn't save ${path.split('/').pop()}:\n${error}`);
+  }
+}
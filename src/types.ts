@@ -25,9 +25,51 @@ export type OutlineParaType = {
   link: number;
 };
 export type SearchResultType = {
-  text: string;
+  link: number;
   index: number;
   query_index: number;
+  // where the match is in the paragraph's text, in characters
+  start: number;
+  end: number;
+  // the runs it's in
+  runs: { start: number; end: number };
+  snippet: SnippetType;
+};
+export type SnippetType = {
+  runs: RunType[];
+  // where the snippet starts in the paragraph's text, in characters
+  start: number;
+  cut_start: boolean;
+  cut_end: boolean;
+};
+
+export type DocumentInfo = {
+  title: string | null;
+  creator: string | null;
+  last_modified_by: string | null;
+  created: string | null;
+  modified: string | null;
+  revision: string | null;
+  pages: number | null;
+  words: number | null;
+  application: string | null;
+  custom: { name: string; value: string }[];
+};
+export type LoadResult = {
+  info: DocumentInfo;
+  warnings: string[];
+};
+// why load_file or load_encrypted_file failed
+export type LoadFailure = {
+  kind: 'password_required' | 'wrong_password' | 'other';
+  message: string;
+};
+
+export type SpeechSection = {
+  title: string;
+  path: string;
+  outline_level: number | null;
+  para_count: number;
 };
 
 export type LoaderState = {
@@ -39,6 +81,9 @@ export type Query = {
   text: string;
   matchCase: boolean;
   onlyOutline: boolean;
+  regex: boolean;
+  wholeWord: boolean;
+  ignoreDiacritics: boolean;
 };
 
 export enum Align {